@@ -1,4 +1,6 @@
-use std::fs;
+use std::{fs, str::FromStr};
+
+const DEFAULT_ROUNDS: usize = 3;
 
 #[allow(unused)]
 const SAMPLE: &str = "A Y
@@ -31,7 +33,7 @@ pub fn run_part_two() -> Result<i32, String> {
     Ok(result)
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum RockPaper {
     Rock = 1,
     Paper = 2,
@@ -54,7 +56,7 @@ impl Outcome {
             (Outcome::Lose, RockPaper::Rock) => RockPaper::Scisors,
             (Outcome::Lose, RockPaper::Paper) => RockPaper::Rock,
             (Outcome::Lose, RockPaper::Scisors) => RockPaper::Paper,
-            (Outcome::Draw, x) => x.clone(),
+            (Outcome::Draw, x) => *x,
         }
     }
 }
@@ -96,3 +98,321 @@ impl std::ops::Add<RockPaper> for RockPaper {
         }) + rhs as i32
     }
 }
+
+impl RockPaper {
+    fn beaten_by(&self) -> RockPaper {
+        Outcome::Win.chosen_outcome(self)
+    }
+
+    fn letter(&self) -> char {
+        match self {
+            RockPaper::Rock => 'A',
+            RockPaper::Paper => 'B',
+            RockPaper::Scisors => 'C',
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum GuideMode {
+    Shape,
+    Outcome,
+}
+
+fn parse_guide(input: &str, mode: GuideMode) -> Result<Vec<RockPaper>, String> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(
+            |(index, line)| match line.split_whitespace().collect::<Vec<&str>>()[..] {
+                [opponent @ ("A" | "B" | "C"), own @ ("X" | "Y" | "Z")] => Ok(match mode {
+                    GuideMode::Shape => RockPaper::from(own),
+                    GuideMode::Outcome => {
+                        Outcome::from(own).chosen_outcome(&RockPaper::from(opponent))
+                    }
+                }),
+                _ => Err(format!(
+                    "line {} : badly formatted guide entry {:?}",
+                    index + 1,
+                    line
+                )),
+            },
+        )
+        .collect()
+}
+
+trait Strategy {
+    fn play(&mut self, round: usize) -> RockPaper;
+    fn observe(&mut self, _opponent: &RockPaper) {}
+    fn reset(&mut self) {}
+    fn guide_length(&self) -> Option<usize> {
+        None
+    }
+}
+
+struct FixedGuide(Vec<RockPaper>);
+
+impl Strategy for FixedGuide {
+    fn play(&mut self, round: usize) -> RockPaper {
+        self.0[round % self.0.len()]
+    }
+
+    fn guide_length(&self) -> Option<usize> {
+        Some(self.0.len())
+    }
+}
+
+struct SeededRandom {
+    seed: u64,
+    state: u64,
+}
+
+impl SeededRandom {
+    fn new(seed: u64) -> Self {
+        // xorshift never leaves the zero state, so nudge it away from there
+        let seed = seed.max(1);
+        SeededRandom { seed, state: seed }
+    }
+}
+
+impl Strategy for SeededRandom {
+    fn play(&mut self, _round: usize) -> RockPaper {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        match self.state % 3 {
+            0 => RockPaper::Rock,
+            1 => RockPaper::Paper,
+            _ => RockPaper::Scisors,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.state = self.seed;
+    }
+}
+
+#[derive(Default)]
+struct FrequencyCounter([usize; 3]);
+
+impl Strategy for FrequencyCounter {
+    fn play(&mut self, _round: usize) -> RockPaper {
+        let [rock, paper, scisors] = self.0;
+        let most_frequent = if rock >= paper && rock >= scisors {
+            RockPaper::Rock
+        } else if paper >= scisors {
+            RockPaper::Paper
+        } else {
+            RockPaper::Scisors
+        };
+        most_frequent.beaten_by()
+    }
+
+    fn observe(&mut self, opponent: &RockPaper) {
+        self.0[*opponent as usize - 1] += 1;
+    }
+
+    fn reset(&mut self) {
+        self.0 = [0; 3];
+    }
+}
+
+#[derive(Default)]
+struct BeatLastMove(Option<RockPaper>);
+
+impl Strategy for BeatLastMove {
+    fn play(&mut self, _round: usize) -> RockPaper {
+        self.0.map_or(RockPaper::Rock, |last| last.beaten_by())
+    }
+
+    fn observe(&mut self, opponent: &RockPaper) {
+        self.0 = Some(*opponent);
+    }
+
+    fn reset(&mut self) {
+        self.0 = None;
+    }
+}
+
+struct Player {
+    name: String,
+    strategy: Box<dyn Strategy>,
+}
+
+impl FromStr for Player {
+    type Err = String;
+
+    // Player specs look like `guide:<path>`, `guide-outcome:<path>`, `random:<seed>`,
+    // `frequency` or `beat-last`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, argument) = s.split_once(':').unwrap_or((s, ""));
+        let strategy: Box<dyn Strategy> = match kind {
+            "guide" | "guide-outcome" => {
+                let mode = match kind {
+                    "guide" => GuideMode::Shape,
+                    _ => GuideMode::Outcome,
+                };
+                let input =
+                    fs::read_to_string(argument).map_err(|err| format!("{argument} : {}", err))?;
+                let guide = parse_guide(&input, mode).map_err(|err| format!("{argument} {err}"))?;
+                if guide.is_empty() {
+                    return Err(format!("{argument} : empty guide"));
+                }
+                Box::new(FixedGuide(guide))
+            }
+            "random" => Box::new(SeededRandom::new(
+                argument
+                    .parse::<u64>()
+                    .map_err(|err| format!("Bad seed {:?} : {}", argument, err))?,
+            )),
+            "frequency" => Box::<FrequencyCounter>::default(),
+            "beat-last" => Box::<BeatLastMove>::default(),
+            _ => return Err(format!("Unknown player strategy {:?}", s)),
+        };
+        Ok(Player {
+            name: s.to_string(),
+            strategy,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct RoundLog {
+    players: (usize, usize),
+    round: usize,
+    hands: (RockPaper, RockPaper),
+    scores: (i32, i32),
+}
+
+#[derive(Debug, Default, Clone)]
+struct Standing {
+    name: String,
+    score: i32,
+    wins: u32,
+    draws: u32,
+    losses: u32,
+}
+
+impl Standing {
+    fn record(&mut self, score: i32, outcome: i32) {
+        self.score += score;
+        match outcome {
+            6 => self.wins += 1,
+            3 => self.draws += 1,
+            _ => self.losses += 1,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TournamentResult {
+    standings: Vec<Standing>,
+    logs: Vec<RoundLog>,
+}
+
+fn play_tournament(players: &mut [Player], rounds: usize) -> TournamentResult {
+    let mut standings = players
+        .iter()
+        .map(|player| Standing {
+            name: player.name.clone(),
+            ..Standing::default()
+        })
+        .collect::<Vec<Standing>>();
+    let mut logs = vec![];
+    for first in 0..players.len() {
+        for second in first + 1..players.len() {
+            players[first].strategy.reset();
+            players[second].strategy.reset();
+            for round in 0..rounds {
+                let hand_one = players[first].strategy.play(round);
+                let hand_two = players[second].strategy.play(round);
+                players[first].strategy.observe(&hand_two);
+                players[second].strategy.observe(&hand_one);
+                // `lhs + rhs` scores the round from the point of view of `rhs`
+                let score_one = hand_two + hand_one;
+                let score_two = hand_one + hand_two;
+                let outcome_one = score_one - hand_one as i32;
+                standings[first].record(score_one, outcome_one);
+                standings[second].record(score_two, 6 - outcome_one);
+                logs.push(RoundLog {
+                    players: (first, second),
+                    round,
+                    hands: (hand_one, hand_two),
+                    scores: (score_one, score_two),
+                });
+            }
+        }
+    }
+    TournamentResult { standings, logs }
+}
+
+impl std::fmt::Display for TournamentResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for log in self.logs.iter() {
+            let (first, second) = log.players;
+            writeln!(
+                f,
+                "{} vs {} round {} : {} {} -> {} {}",
+                self.standings[first].name,
+                self.standings[second].name,
+                log.round + 1,
+                log.hands.0.letter(),
+                log.hands.1.letter(),
+                log.scores.0,
+                log.scores.1
+            )?;
+        }
+        let mut standings = self.standings.clone();
+        standings.sort_by_key(|standing| std::cmp::Reverse(standing.score));
+        let width = standings
+            .iter()
+            .map(|s| s.name.len())
+            .max()
+            .unwrap_or(0)
+            .max(6);
+        writeln!(
+            f,
+            "{:<width$} {:>7} {:>4} {:>4} {:>4}",
+            "player", "score", "W", "D", "L"
+        )?;
+        for standing in standings {
+            writeln!(
+                f,
+                "{:<width$} {:>7} {:>4} {:>4} {:>4}",
+                standing.name, standing.score, standing.wins, standing.draws, standing.losses
+            )?;
+        }
+        Ok(())
+    }
+}
+
+pub fn run_tournament(args: &[String]) -> Result<TournamentResult, String> {
+    let mut rounds = None;
+    let mut players = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--rounds" => {
+                rounds = Some(
+                    args.next()
+                        .ok_or("--rounds expects a value")?
+                        .parse::<usize>()
+                        .map_err(|err| err.to_string())?,
+                )
+            }
+            spec => players.push(spec.parse::<Player>()?),
+        }
+    }
+    if players.len() < 2 {
+        return Err(String::from("A tournament needs at least two players"));
+    }
+    let rounds = rounds.unwrap_or_else(|| {
+        players
+            .iter()
+            .filter_map(|player| player.strategy.guide_length())
+            .max()
+            .unwrap_or(DEFAULT_ROUNDS)
+    });
+    Ok(play_tournament(&mut players, rounds))
+}
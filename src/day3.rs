@@ -1,10 +1,10 @@
 use std::fs;
 
-const UPPERCASE_PRIORITY: u8 = 27;
-const LOWERCASE_PRIORITY: u8 = 1;
+const UPPERCASE_PRIORITY: u32 = 27;
+const LOWERCASE_PRIORITY: u32 = 1;
 
-const CHARCODE_LOWERBOUND: u8 = 97;
-const CHARCODE_UPPERBOUND: u8 = 65;
+// Bit `n` stands for the item of priority `n`, so bits 1 to 52 are in use
+const ALL_ITEMS: u64 = ((1 << 53) - 1) & !1;
 
 #[allow(unused)]
 const SAMPLE: &str = "vJrwpWtwJgWrhcsFMMfFFhFp
@@ -14,52 +14,355 @@ wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn
 ttgJtRGJQctTZtZT
 CrZsJsPPZsGzwwsLwLmpwMDw";
 
-fn get_common_item(part_one: &str, part_two: &str) -> Option<char> {
-    let res = part_one
-        .chars()
-        .find(|a| part_two.contains(|element| element == *a));
-    res
+fn priority(item: char) -> Option<u32> {
+    match item {
+        'a'..='z' => Some(LOWERCASE_PRIORITY + item as u32 - 'a' as u32),
+        'A'..='Z' => Some(UPPERCASE_PRIORITY + item as u32 - 'A' as u32),
+        _ => None,
+    }
+}
+
+fn item(priority: u32) -> char {
+    match priority {
+        LOWERCASE_PRIORITY..=26 => (b'a' + (priority - LOWERCASE_PRIORITY) as u8) as char,
+        _ => (b'A' + (priority - UPPERCASE_PRIORITY) as u8) as char,
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct ItemSet(u64);
+
+impl ItemSet {
+    fn intersection(self, other: ItemSet) -> ItemSet {
+        ItemSet(self.0 & other.0)
+    }
+
+    fn union(self, other: ItemSet) -> ItemSet {
+        ItemSet(self.0 | other.0)
+    }
+
+    fn len(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    fn priorities(self) -> impl Iterator<Item = u32> {
+        (LOWERCASE_PRIORITY..=52).filter(move |priority| self.0 & (1 << priority) != 0)
+    }
+
+    fn items(self) -> impl Iterator<Item = char> {
+        self.priorities().map(item)
+    }
+
+    fn priority_sum(self) -> u32 {
+        self.priorities().sum()
+    }
+
+    fn from_items(items: impl IntoIterator<Item = char>) -> Result<ItemSet, String> {
+        items.into_iter().try_fold(ItemSet::default(), |set, item| {
+            priority(item)
+                .map(|priority| ItemSet(set.0 | 1 << priority))
+                .ok_or_else(|| format!("Invalid item {:?}", item))
+        })
+    }
+}
+
+fn common_items(sets: impl IntoIterator<Item = ItemSet>) -> ItemSet {
+    sets.into_iter()
+        .fold(ItemSet(ALL_ITEMS), |acc, set| acc.intersection(set))
+}
+
+#[derive(Debug)]
+struct Rucksack(Vec<ItemSet>);
+
+impl Rucksack {
+    fn parse(line: &str, compartments: usize) -> Result<Self, String> {
+        let items = line.chars().collect::<Vec<char>>();
+        if compartments == 0 || !items.len().is_multiple_of(compartments) {
+            return Err(format!(
+                "{} items cannot be split into {} compartments",
+                items.len(),
+                compartments
+            ));
+        }
+        let size = items.len() / compartments;
+        (0..compartments)
+            .map(|index| {
+                ItemSet::from_items(items[index * size..(index + 1) * size].iter().copied())
+            })
+            .collect::<Result<Vec<ItemSet>, String>>()
+            .map(Rucksack)
+    }
+
+    fn items(&self) -> ItemSet {
+        self.0
+            .iter()
+            .fold(ItemSet::default(), |acc, set| acc.union(*set))
+    }
+
+    fn shared_items(&self) -> ItemSet {
+        common_items(self.0.iter().copied())
+    }
 }
 
-pub fn run_part_one() {
-    // let x = SAMPLE
-    let result = fs::read_to_string("src/day3.input")
-        .unwrap()
+fn parse_rucksacks(input: &str, compartments: usize) -> Result<Vec<Rucksack>, String> {
+    input
         .lines()
-        .map(|backpack: &str| -> u32 {
-            let length = backpack.len();
-            get_common_item(&backpack[0..length / 2], &backpack[length / 2..])
-                .map(|item| match item.is_lowercase() {
-                    true => LOWERCASE_PRIORITY + item as u8 - CHARCODE_LOWERBOUND,
-                    false => UPPERCASE_PRIORITY + item as u8 - CHARCODE_UPPERBOUND,
-                })
-                .unwrap()
-                .into()
+        .enumerate()
+        .map(|(index, line)| {
+            Rucksack::parse(line, compartments).map_err(|err| format!("line {} : {err}", index + 1))
         })
-        .sum::<u32>();
-    println!("{result}");
-}
-
-pub fn run_part_two() -> Option<u32> {
-    let bags = fs::read_to_string("src/day3.input").unwrap();
-    let mut bags = bags.lines();
-    let mut sum = 0;
-    while let Some(backpack) = bags.next() {
-        sum += bags.next().and_then(|x| {
-            bags.next().and_then(|y| {
-                backpack
-                    .chars()
-                    .find(|item| {
-                        x.contains(|element| element == *item)
-                            && y.contains(|element| element == *item)
+        .collect()
+}
+
+fn group_badges(rucksacks: &[Rucksack], group_size: usize) -> Vec<ItemSet> {
+    rucksacks
+        .chunks(group_size)
+        .map(|group| common_items(group.iter().map(Rucksack::items)))
+        .collect()
+}
+
+#[derive(Debug, PartialEq)]
+enum Issue {
+    InvalidItem { column: usize, item: char },
+    OddLength(usize),
+    NoSharedItem,
+    MultipleSharedItems(Vec<char>),
+    IncompleteGroup(usize),
+    NoBadge,
+    MultipleBadges(Vec<char>),
+}
+
+#[derive(Debug, PartialEq)]
+struct Problem {
+    line: usize,
+    issue: Issue,
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {} : ", self.line)?;
+        match self.issue {
+            Issue::InvalidItem { column, item } => {
+                write!(f, "invalid item {:?} at column {}", item, column)
+            }
+            Issue::OddLength(length) => write!(f, "odd number of items ({length})"),
+            Issue::NoSharedItem => write!(f, "no item shared between compartments"),
+            Issue::MultipleSharedItems(ref items) => {
+                write!(f, "several items shared between compartments {:?}", items)
+            }
+            Issue::IncompleteGroup(size) => write!(f, "group only has {size} rucksacks"),
+            Issue::NoBadge => write!(f, "group has no badge"),
+            Issue::MultipleBadges(ref items) => write!(f, "group has several badges {:?}", items),
+        }
+    }
+}
+
+fn validate(input: &str, check_compartments: bool, group_size: Option<usize>) -> Vec<Problem> {
+    let mut problems = vec![];
+    let rucksacks = input
+        .lines()
+        .enumerate()
+        .map(|(index, line)| {
+            let line_number = index + 1;
+            let problem_count = problems.len();
+            line.chars()
+                .enumerate()
+                .filter(|(_, item)| priority(*item).is_none())
+                .for_each(|(column, item)| {
+                    problems.push(Problem {
+                        line: line_number,
+                        issue: Issue::InvalidItem {
+                            column: column + 1,
+                            item,
+                        },
                     })
-                    .map(|item| match item.is_lowercase() {
-                        true => LOWERCASE_PRIORITY + item as u8 - CHARCODE_LOWERBOUND,
-                        false => UPPERCASE_PRIORITY + item as u8 - CHARCODE_UPPERBOUND,
+                });
+            if check_compartments {
+                let length = line.chars().count();
+                if length % 2 != 0 {
+                    problems.push(Problem {
+                        line: line_number,
+                        issue: Issue::OddLength(length),
                     })
+                }
+            }
+            if problems.len() > problem_count {
+                return None;
+            }
+            if check_compartments {
+                let shared = Rucksack::parse(line, 2).ok()?.shared_items();
+                let issue = match shared.len() {
+                    0 => Some(Issue::NoSharedItem),
+                    1 => None,
+                    _ => Some(Issue::MultipleSharedItems(shared.items().collect())),
+                };
+                if let Some(issue) = issue {
+                    problems.push(Problem {
+                        line: line_number,
+                        issue,
+                    });
+                }
+            }
+            Rucksack::parse(line, 1).ok()
+        })
+        .collect::<Vec<Option<Rucksack>>>();
+
+    if let Some(group_size) = group_size.filter(|size| *size > 0) {
+        rucksacks
+            .chunks(group_size)
+            .enumerate()
+            .for_each(|(index, group)| {
+                let line = index * group_size + 1;
+                if group.len() < group_size {
+                    return problems.push(Problem {
+                        line,
+                        issue: Issue::IncompleteGroup(group.len()),
+                    });
+                }
+                if let Some(group) = group
+                    .iter()
+                    .map(Option::as_ref)
+                    .collect::<Option<Vec<&Rucksack>>>()
+                {
+                    let badge = common_items(group.iter().map(|rucksack| rucksack.items()));
+                    let issue = match badge.len() {
+                        0 => Issue::NoBadge,
+                        1 => return,
+                        _ => Issue::MultipleBadges(badge.items().collect()),
+                    };
+                    problems.push(Problem { line, issue });
+                }
+            });
+    }
+    problems.sort_by_key(|problem| problem.line);
+    problems
+}
+
+fn ensure_valid(problems: Vec<Problem>) -> Result<(), String> {
+    match problems.is_empty() {
+        true => Ok(()),
+        false => Err(problems
+            .iter()
+            .map(|problem| problem.to_string())
+            .collect::<Vec<String>>()
+            .join("\n")),
+    }
+}
+
+#[derive(Debug)]
+struct SharedItems {
+    line: usize,
+    items: Vec<char>,
+}
+
+impl std::fmt::Display for SharedItems {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.items.is_empty() {
+            return write!(f, "line {} : none", self.line);
+        }
+        let items = self
+            .items
+            .iter()
+            .map(|item| format!("{item} ({})", priority(*item).unwrap_or_default()))
+            .collect::<Vec<String>>();
+        write!(f, "line {} : {}", self.line, items.join(", "))
+    }
+}
+
+fn shared_items_report(input: &str) -> Vec<SharedItems> {
+    input
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            Rucksack::parse(line, 2).ok().map(|rucksack| SharedItems {
+                line: index + 1,
+                items: rucksack.shared_items().items().collect(),
             })
-        })? as u32
+        })
+        .collect()
+}
+
+const DEFAULT_GROUP_SIZE: usize = 3;
+
+fn read_input() -> Result<String, String> {
+    fs::read_to_string("src/day3.input").map_err(|err| err.to_string())
+}
+
+fn part_one(input: &str) -> Result<u32, String> {
+    ensure_valid(validate(input, true, None))?;
+    Ok(parse_rucksacks(input, 2)?
+        .iter()
+        .map(|rucksack| rucksack.shared_items().priority_sum())
+        .sum())
+}
+
+fn part_two(input: &str, group_size: usize) -> Result<u32, String> {
+    if group_size == 0 {
+        return Err(String::from("Group size must be at least 1"));
+    }
+    ensure_valid(validate(input, false, Some(group_size)))?;
+    Ok(group_badges(&parse_rucksacks(input, 1)?, group_size)
+        .iter()
+        .map(|badge| badge.priority_sum())
+        .sum())
+}
+
+pub fn run_part_one() -> Result<u32, String> {
+    // let input = SAMPLE;
+    part_one(&read_input()?)
+}
+
+pub fn run_part_two(group_size: usize) -> Result<u32, String> {
+    // let input = SAMPLE;
+    part_two(&read_input()?, group_size)
+}
+
+// Accepts `[validate | shared] [--group N]`, running both parts when no subcommand is given
+pub fn run_command(args: &[String]) -> Result<(), String> {
+    let mut group_size = DEFAULT_GROUP_SIZE;
+    let mut command = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--group" => {
+                group_size = args
+                    .next()
+                    .ok_or("--group expects a value")?
+                    .parse::<usize>()
+                    .map_err(|err| format!("Bad group size : {err}"))?
+            }
+            "validate" | "shared" if command.is_none() => command = Some(arg.as_str()),
+            other => return Err(format!("Unknown argument {:?}", other)),
+        }
+    }
+    match command {
+        Some("validate") => {
+            let problems = validate(&read_input()?, true, Some(group_size));
+            problems.iter().for_each(|problem| println!("{problem}"));
+            println!("{} problem(s) found", problems.len());
+        }
+        Some(_) => shared_items_report(&read_input()?)
+            .iter()
+            .for_each(|report| println!("{report}")),
+        None => {
+            println!("day 3: {:?}", run_part_one());
+            println!("day 3: {:?}", run_part_two(group_size));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn part_one_sample() {
+        assert_eq!(part_one(SAMPLE), Ok(157));
+    }
+
+    #[test]
+    fn part_two_sample() {
+        assert_eq!(part_two(SAMPLE, 3), Ok(70));
     }
-    println!("{sum}");
-    Some(sum)
 }
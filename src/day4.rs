@@ -1,4 +1,11 @@
-use std::{fs, ops::RangeInclusive, str::FromStr};
+use std::{collections::BTreeSet, fs};
+
+use nom::{
+    bytes::complete::tag, character::complete, combinator::all_consuming, multi::separated_list1,
+    sequence::separated_pair, IResult,
+};
+
+use crate::interval::{Interval, IntervalSet};
 
 #[allow(unused)]
 const SAMPLE: &str = "2-4,6-8
@@ -8,70 +15,277 @@ const SAMPLE: &str = "2-4,6-8
 6-6,4-6
 2-6,4-8";
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ReversedRanges {
+    Reject,
+    Normalize,
+}
+
 #[derive(Debug)]
-struct RangeWrapper<T>(RangeInclusive<T>);
+struct Group {
+    line: usize,
+    assignments: Vec<Interval<u32>>,
+}
 
-pub fn run_part_one() {
-    // let overlapping_ranges = SAMPLE
-    let overlapping_ranges = fs::read_to_string("src/day4.input")
-        .unwrap()
-        .lines()
-        .map(|line| {
-            line.split(",")
-                .map(|range| range.parse::<RangeWrapper<u32>>().unwrap())
-                .collect::<Vec<RangeWrapper<u32>>>()
+impl Group {
+    fn count_pairs(&self, predicate: impl Fn(&Interval<u32>, &Interval<u32>) -> bool) -> usize {
+        self.assignments
+            .iter()
+            .enumerate()
+            .flat_map(|(index, first)| {
+                self.assignments[index + 1..]
+                    .iter()
+                    .map(move |second| (first, second))
+            })
+            .filter(|(first, second)| predicate(first, second))
+            .count()
+    }
+
+    fn containing_pairs(&self) -> usize {
+        self.count_pairs(|first, second| {
+            first.contains_interval(second) || second.contains_interval(first)
         })
-        .map(|ranges| ranges[0].overlap(&ranges[1]) || ranges[1].overlap(&ranges[0]))
-        .fold(0, |acc, el| acc + if el { 1 } else { 0 });
-    println!("{overlapping_ranges}")
+    }
+
+    fn overlapping_pairs(&self) -> usize {
+        self.count_pairs(|first, second| first.overlaps(second))
+    }
 }
 
-pub fn run_part_two() {
-    // let overlapping_ranges = SAMPLE
-    let overlapping_ranges = fs::read_to_string("src/day4.input")
-        .unwrap()
+fn parse_range(input: &str) -> IResult<&str, (u32, u32)> {
+    separated_pair(complete::u32, tag("-"), complete::u32)(input)
+}
+
+fn parse_assignments(input: &str) -> IResult<&str, Vec<(u32, u32)>> {
+    all_consuming(separated_list1(tag(","), parse_range))(input)
+}
+
+fn parse_groups(input: &str, reversed: ReversedRanges) -> Result<Vec<Group>, String> {
+    input
         .lines()
-        .map(|line| {
-            line.split(",")
-                .map(|range| range.parse::<RangeWrapper<u32>>().unwrap())
-                .collect::<Vec<RangeWrapper<u32>>>()
+        .enumerate()
+        .map(|(index, line)| {
+            let line_number = index + 1;
+            let (_, ranges) = parse_assignments(line).map_err(|_| {
+                format!(
+                    "line {line_number} : badly formatted assignments {:?}",
+                    line
+                )
+            })?;
+            let assignments = ranges
+                .into_iter()
+                .map(|(start, end)| match (Interval::new(start, end), reversed) {
+                    (Some(interval), _) => Ok(interval),
+                    (None, ReversedRanges::Normalize) => Ok(Interval::new(end, start).unwrap()),
+                    (None, ReversedRanges::Reject) => {
+                        Err(format!("line {line_number} : reversed range {start}-{end}"))
+                    }
+                })
+                .collect::<Result<Vec<Interval<u32>>, String>>()?;
+            Ok(Group {
+                line: line_number,
+                assignments,
+            })
         })
-        .map(|ranges| ranges[0].partial_overlap(&ranges[1]))
-        .fold(0, |acc, el| acc + if el { 1 } else { 0 });
-    println!("{overlapping_ranges}")
+        .collect()
+}
+
+fn read_groups(reversed: ReversedRanges) -> Result<Vec<Group>, String> {
+    // let input = SAMPLE;
+    let input = fs::read_to_string("src/day4.input").map_err(|err| err.to_string())?;
+    parse_groups(&input, reversed)
 }
 
-impl FromStr for RangeWrapper<u32> {
-    type Err = String;
+fn count_groups(
+    reversed: ReversedRanges,
+    predicate: impl Fn(&Group) -> bool,
+) -> Result<usize, String> {
+    Ok(read_groups(reversed)?
+        .iter()
+        .filter(|group| predicate(group))
+        .count())
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let vec_tuple: Vec<u32> = s
-            .split("-")
-            .map(|value| value.parse::<u32>().map_err(|err| err.to_string()))
-            .collect::<Result<Vec<u32>, String>>()?;
-        Ok(RangeWrapper(vec_tuple[0]..=vec_tuple[1]))
+pub fn run_part_one() -> Result<usize, String> {
+    count_groups(ReversedRanges::Reject, |group| group.containing_pairs() > 0)
+}
+
+pub fn run_part_two() -> Result<usize, String> {
+    count_groups(ReversedRanges::Reject, |group| {
+        group.overlapping_pairs() > 0
+    })
+}
+
+#[derive(Debug)]
+struct Elf {
+    id: usize,
+    line: usize,
+    sections: Interval<u32>,
+}
+
+fn elves(groups: &[Group]) -> Vec<Elf> {
+    groups
+        .iter()
+        .flat_map(|group| {
+            group
+                .assignments
+                .iter()
+                .map(move |sections| (group.line, *sections))
+        })
+        .enumerate()
+        .map(|(index, (line, sections))| Elf {
+            id: index + 1,
+            line,
+            sections,
+        })
+        .collect()
+}
+
+// Sweeps the assignments by start section, keeping the ones still open ordered by their
+// end section: every elf still open when another one starts overlaps with it
+fn overlapping_pairs(elves: &[Elf]) -> Vec<(usize, usize)> {
+    let mut order = (0..elves.len()).collect::<Vec<usize>>();
+    order.sort_by_key(|index| elves[*index].sections.start());
+    let mut open: BTreeSet<(u32, usize)> = BTreeSet::new();
+    let mut pairs = vec![];
+    for index in order {
+        let sections = &elves[index].sections;
+        while open.first().is_some_and(|(end, _)| *end < sections.start()) {
+            open.pop_first();
+        }
+        pairs.extend(open.iter().map(|(_, other)| {
+            let (first, second) = (elves[*other].id, elves[index].id);
+            (first.min(second), first.max(second))
+        }));
+        open.insert((sections.end(), index));
+    }
+    pairs.sort();
+    pairs
+}
+
+// Returns the highest number of elves assigned to a single section and where it happens
+fn max_coverage(elves: &[Elf]) -> (usize, IntervalSet<u32>) {
+    let mut events = elves
+        .iter()
+        .flat_map(|elf| {
+            [
+                (elf.sections.start() as u64, 1),
+                (elf.sections.end() as u64 + 1, -1),
+            ]
+        })
+        .collect::<Vec<(u64, i64)>>();
+    events.sort();
+    let (mut coverage, mut max) = (0, 0);
+    let mut sections = IntervalSet::new();
+    for (position, event) in events.iter().enumerate() {
+        coverage += event.1;
+        let next = match events.get(position + 1) {
+            Some(next) if next.0 > event.0 => next.0,
+            _ => continue,
+        };
+        if coverage > max {
+            max = coverage;
+            sections = IntervalSet::new();
+        }
+        if coverage == max && max > 0 {
+            sections.insert(Interval::new(event.0 as u32, (next - 1) as u32).unwrap());
+        }
     }
+    (max as usize, sections)
 }
 
-trait Overlap {
-    fn overlap(&self, t: &Self) -> bool;
+#[derive(Debug)]
+struct RosterAnalysis {
+    elves: Vec<Elf>,
+    pairs: Vec<(usize, usize)>,
+    max_coverage: usize,
+    max_coverage_sections: IntervalSet<u32>,
+    uncovered: IntervalSet<u32>,
 }
 
-impl Overlap for RangeWrapper<u32> {
-    fn overlap(&self, t: &Self) -> bool {
-        self.0.contains(&t.0.start()) && self.0.contains(t.0.end())
+fn analyze_roster(groups: &[Group]) -> RosterAnalysis {
+    let elves = elves(groups);
+    let pairs = overlapping_pairs(&elves);
+    let (max_coverage, max_coverage_sections) = max_coverage(&elves);
+    let covered = elves
+        .iter()
+        .map(|elf| elf.sections)
+        .collect::<IntervalSet<u32>>();
+    let uncovered = match (covered.intervals().first(), covered.intervals().last()) {
+        (Some(first), Some(last)) => {
+            covered.gaps(&Interval::new(first.start(), last.end()).unwrap())
+        }
+        _ => IntervalSet::new(),
+    };
+    RosterAnalysis {
+        elves,
+        pairs,
+        max_coverage,
+        max_coverage_sections,
+        uncovered,
     }
 }
 
-trait PartialOverlap {
-    fn partial_overlap(&self, t: &Self) -> bool;
+impl std::fmt::Display for RosterAnalysis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let describe = |id: usize| {
+            let elf = &self.elves[id - 1];
+            format!("elf {} (line {}, {})", elf.id, elf.line, elf.sections)
+        };
+        for (first, second) in self.pairs.iter() {
+            writeln!(f, "{} overlaps {}", describe(*first), describe(*second))?;
+        }
+        writeln!(f, "{} overlapping pairs", self.pairs.len())?;
+        writeln!(
+            f,
+            "at most {} elves on sections {}",
+            self.max_coverage, self.max_coverage_sections
+        )?;
+        match self.uncovered.is_empty() {
+            true => writeln!(f, "every section is covered"),
+            false => writeln!(
+                f,
+                "{} sections nobody covers : {}",
+                self.uncovered.len(),
+                self.uncovered
+            ),
+        }
+    }
 }
 
-impl PartialOverlap for RangeWrapper<u32> {
-    fn partial_overlap(&self, t: &Self) -> bool {
-        self.0.contains(t.0.end())
-            || self.0.contains(t.0.start())
-            || t.0.contains(self.0.end())
-            || t.0.contains(self.0.start())
+// Accepts `roster` or `groups`, optionally followed by `--normalize` to accept reversed ranges
+pub fn run_command(args: &[String]) -> Result<(), String> {
+    let (command, reversed) = match args.iter().map(|arg| arg.as_str()).collect::<Vec<&str>>()[..] {
+        [] => (None, ReversedRanges::Reject),
+        ["--normalize"] => (None, ReversedRanges::Normalize),
+        [command] => (Some(command), ReversedRanges::Reject),
+        [command, "--normalize"] => (Some(command), ReversedRanges::Normalize),
+        _ => return Err(format!("Unknown arguments {:?}", args)),
+    };
+    let Some(command) = command else {
+        println!(
+            "day 4: {:?}",
+            count_groups(reversed, |group| group.containing_pairs() > 0)
+        );
+        println!(
+            "day 4: {:?}",
+            count_groups(reversed, |group| group.overlapping_pairs() > 0)
+        );
+        return Ok(());
+    };
+    let groups = read_groups(reversed)?;
+    match command {
+        "roster" => print!("{}", analyze_roster(&groups)),
+        "groups" => groups.iter().for_each(|group| {
+            println!(
+                "line {} : {} assignments, {} containing pairs, {} overlapping pairs",
+                group.line,
+                group.assignments.len(),
+                group.containing_pairs(),
+                group.overlapping_pairs()
+            )
+        }),
+        _ => return Err(format!("Unknown command {:?}", command)),
     }
+    Ok(())
 }
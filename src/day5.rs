@@ -1,4 +1,4 @@
-use std::{collections::HashMap, fs, str::FromStr};
+use std::{fs, str::FromStr};
 
 #[allow(unused)]
 const SAMPLE: &str = "    [D]    
@@ -11,169 +11,564 @@ move 3 from 1 to 3
 move 2 from 2 to 1
 move 1 from 1 to 2";
 
-pub fn run_part_one() -> Result<(), String> {
-    let mut column_map: HashMap<u32, Vec<String>> = HashMap::new();
-    let parts = fs::read_to_string("src/day5.input").unwrap();
-    let parts = parts.split("\n\n").collect::<Vec<&str>>();
-    let (placement, moves) = (parts[0], parts[1]);
-    let mut placements = placement.lines().collect::<Vec<&str>>();
-    placements.pop();
-    let placements = placements
-        .iter()
-        .map(|line| parse_line(line))
-        .collect::<Vec<Vec<Option<String>>>>();
-    placements.into_iter().for_each(|line_vec| {
-        line_vec
-            .into_iter()
-            .enumerate()
-            .for_each(|(index, element)| {
-                if element.is_some() {
-                    let element = element.unwrap();
-                    let cloned_element = element.clone();
-                    column_map
-                        .entry((index + 1) as u32)
-                        .and_modify(|vec_val| vec_val.push(element))
-                        .or_insert(vec![cloned_element]);
-                }
+#[derive(Debug, Clone, PartialEq)]
+struct Stack {
+    id: u32,
+    crates: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Stacks(Vec<Stack>);
+
+impl Stacks {
+    fn get_mut(&mut self, id: u32) -> Option<&mut Vec<String>> {
+        self.0
+            .iter_mut()
+            .find(|stack| stack.id == id)
+            .map(|stack| &mut stack.crates)
+    }
+
+    fn top_crates(&self) -> String {
+        self.0
+            .iter()
+            .filter_map(|stack| stack.crates.last())
+            .map(|label| label.as_str())
+            .collect()
+    }
+
+    fn get(&self, id: u32) -> Option<&Vec<String>> {
+        self.0
+            .iter()
+            .find(|stack| stack.id == id)
+            .map(|stack| &stack.crates)
+    }
+
+    fn check(&self, mov: &Move) -> Result<(), MoveError> {
+        let Move(count, from, to) = *mov;
+        let source = self.get(from).ok_or(MoveError::MissingSource(from))?;
+        self.get(to).ok_or(MoveError::MissingDestination(to))?;
+        match source.len() < count as usize {
+            true => Err(MoveError::NotEnoughCrates {
+                stack: from,
+                wanted: count,
+                available: source.len(),
+            }),
+            false => Ok(()),
+        }
+    }
+
+    // Returns the crates lifted off the source stack, bottom crate first
+    fn apply(&mut self, mov: &Move, crane: &dyn Crane) -> Option<Vec<String>> {
+        let Move(count, from, to) = mov;
+        self.get_mut(*to)?;
+        let taken = self.get_mut(*from).map(|from_col| {
+            let count = (*count as usize).min(from_col.len());
+            from_col.split_off(from_col.len() - count)
+        })?;
+        self.get_mut(*to)?
+            .append(&mut crane.transfer(taken.clone()));
+        Some(taken)
+    }
+
+    fn undo(&mut self, mov: &Move, mut taken: Vec<String>) {
+        let Move(_, from, to) = mov;
+        if let Some(to_col) = self.get_mut(*to) {
+            to_col.truncate(to_col.len() - taken.len());
+        }
+        if let Some(from_col) = self.get_mut(*from) {
+            from_col.append(&mut taken);
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum MoveError {
+    MissingSource(u32),
+    MissingDestination(u32),
+    NotEnoughCrates {
+        stack: u32,
+        wanted: u32,
+        available: usize,
+    },
+}
+
+impl std::fmt::Display for MoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoveError::MissingSource(id) => write!(f, "source stack {id} does not exist"),
+            MoveError::MissingDestination(id) => {
+                write!(f, "destination stack {id} does not exist")
+            }
+            MoveError::NotEnoughCrates {
+                stack,
+                wanted,
+                available,
+            } => write!(
+                f,
+                "stack {stack} only has {available} crates, {wanted} requested"
+            ),
+        }
+    }
+}
+
+impl Stack {
+    // Wide enough for the stack number and every label with its brackets
+    fn width(&self) -> usize {
+        self.crates
+            .iter()
+            .map(|label| label.len() + 2)
+            .chain([self.id.to_string().len(), 3])
+            .max()
+            .unwrap_or(3)
+    }
+}
+
+impl std::fmt::Display for Stacks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let widths = self.0.iter().map(Stack::width).collect::<Vec<usize>>();
+        let height = self
+            .0
+            .iter()
+            .map(|stack| stack.crates.len())
+            .max()
+            .unwrap_or(0);
+        for row in (0..height).rev() {
+            let cells = self
+                .0
+                .iter()
+                .zip(widths.iter())
+                .map(|(stack, width)| match stack.crates.get(row) {
+                    Some(label) => format!("{:<width$}", format!("[{label}]")),
+                    None => " ".repeat(*width),
+                })
+                .collect::<Vec<String>>();
+            writeln!(f, "{}", cells.join(" "))?;
+        }
+        let footer = self
+            .0
+            .iter()
+            .zip(widths.iter())
+            .map(|(stack, width)| {
+                let id = stack.id.to_string();
+                let left = (width - id.len()).div_ceil(2);
+                format!(
+                    "{}{id}{}",
+                    " ".repeat(left),
+                    " ".repeat(width - id.len() - left)
+                )
             })
-    });
-    column_map.iter_mut().for_each(|(_, value)| value.reverse());
-
-    let moves = moves
-        .lines()
-        .map(|line| line.parse::<Move>())
-        .collect::<Result<Vec<Move>, String>>()?;
-
-    moves.iter().for_each(|mov| {
-        mov.execute(&mut column_map);
-    });
-    let mut res = column_map
-        .iter()
-        .map(|(key, value)| (*key, value.last()))
-        .collect::<Vec<(u32, Option<&String>)>>();
-    res.sort();
-    let res = res.into_iter().fold(String::from(""), |mut acc, val| {
-        acc.push_str(val.1.unwrap());
-        acc
-    });
-    println!("{:?}", res.replace("[", "").replace("]", ""));
-    Ok(())
+            .collect::<Vec<String>>();
+        write!(f, "{}", footer.join(" "))
+    }
 }
 
-pub fn run_part_two() -> Result<(), String> {
-    let mut column_map: HashMap<u32, Vec<String>> = HashMap::new();
-    let parts = fs::read_to_string("src/day5.input").unwrap();
-    let parts = parts.split("\n\n").collect::<Vec<&str>>();
-    let (placement, moves) = (parts[0], parts[1]);
-    let mut placements = placement.lines().collect::<Vec<&str>>();
-    placements.pop();
-    let placements = placements
-        .iter()
-        .map(|line| parse_line(line))
-        .collect::<Vec<Vec<Option<String>>>>();
-    placements.into_iter().for_each(|line_vec| {
-        line_vec
-            .into_iter()
+// A crane takes the crates lifted from the top of a stack, bottom crate first, and
+// returns them in the order they end up stacked on the destination
+trait Crane {
+    fn name(&self) -> String;
+    fn transfer(&self, crates: Vec<String>) -> Vec<String>;
+}
+
+struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn name(&self) -> String {
+        String::from("CrateMover 9000")
+    }
+
+    fn transfer(&self, mut crates: Vec<String>) -> Vec<String> {
+        crates.reverse();
+        crates
+    }
+}
+
+struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn name(&self) -> String {
+        String::from("CrateMover 9001")
+    }
+
+    fn transfer(&self, crates: Vec<String>) -> Vec<String> {
+        crates
+    }
+}
+
+// Lifts at most `capacity` crates at a time, keeping their order within a lift
+struct CapacityLimited(usize);
+
+impl Crane for CapacityLimited {
+    fn name(&self) -> String {
+        format!("capacity-limited crane ({})", self.0)
+    }
+
+    fn transfer(&self, crates: Vec<String>) -> Vec<String> {
+        crates
+            .rchunks(self.0)
+            .flat_map(|lift| lift.iter().cloned())
+            .collect()
+    }
+}
+
+// Lifts every crate at once but flips each run of `chunk` crates, counting from the bottom
+struct ChunkReversing(usize);
+
+impl Crane for ChunkReversing {
+    fn name(&self) -> String {
+        format!("chunk-reversing crane ({})", self.0)
+    }
+
+    fn transfer(&self, crates: Vec<String>) -> Vec<String> {
+        crates
+            .chunks(self.0)
+            .flat_map(|chunk| chunk.iter().rev().cloned())
+            .collect()
+    }
+}
+
+impl FromStr for Box<dyn Crane> {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, size) = s.split_once(':').unwrap_or((s, ""));
+        let size = || match size.parse::<usize>() {
+            Ok(size) if size > 0 => Ok(size),
+            _ => Err(format!("Bad crane size {:?}", size)),
+        };
+        match kind {
+            "9000" => Ok(Box::new(CrateMover9000)),
+            "9001" => Ok(Box::new(CrateMover9001)),
+            "capacity" => Ok(Box::new(CapacityLimited(size()?))),
+            "chunk" => Ok(Box::new(ChunkReversing(size()?))),
+            _ => Err(format!("Unknown crane {:?}", s)),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Simulator {
+    stacks: Stacks,
+    moves: Vec<Move>,
+}
+
+impl FromStr for Simulator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (placement, moves) = s
+            .split_once("\n\n")
+            .ok_or("Missing blank line between drawing and moves")?;
+        let stacks = parse_drawing(placement)?;
+        let drawing_lines = placement.lines().count() + 1;
+        let moves = moves
+            .lines()
             .enumerate()
-            .for_each(|(index, element)| {
-                if element.is_some() {
-                    let element = element.unwrap();
-                    let cloned_element = element.clone();
-                    column_map
-                        .entry((index + 1) as u32)
-                        .and_modify(|vec_val| vec_val.push(element))
-                        .or_insert(vec![cloned_element]);
+            .map(|(index, line)| {
+                let line_number = drawing_lines + index + 1;
+                let mov = line
+                    .parse::<Move>()
+                    .map_err(|err| format!("line {line_number} : {err}"))?;
+                match [mov.1, mov.2]
+                    .into_iter()
+                    .find(|id| stacks.get(*id).is_none())
+                {
+                    Some(id) => Err(format!("line {line_number} : unknown stack {id}")),
+                    None => Ok(mov),
                 }
             })
-    });
-    column_map.iter_mut().for_each(|(_, value)| value.reverse());
+            .collect::<Result<Vec<Move>, String>>()?;
+        Ok(Simulator { stacks, moves })
+    }
+}
 
-    let moves = moves
-        .lines()
-        .map(|line| line.parse::<Move>())
-        .collect::<Result<Vec<Move>, String>>()?;
+impl Simulator {
+    fn run(&self, crane: &dyn Crane) -> Result<Stacks, String> {
+        let mut replay = Replay::new(self, crane, OnInvalidMove::Fail);
+        replay.jump_to(self.moves.len())?;
+        Ok(replay.stacks)
+    }
+}
 
-    moves
-        .iter()
-        .for_each(|mov| match mov.execute_9001(&mut column_map) {
-            None => println!("an error occurred during move"),
-            _ => (),
-        });
-    let mut res = column_map
-        .iter()
-        .map(|(key, value)| (*key, value.last()))
-        .collect::<Vec<(u32, Option<&String>)>>();
-    res.sort();
-    let res = res.into_iter().fold(String::from(""), |mut acc, val| {
-        acc.push_str(val.1.unwrap());
-        acc
-    });
-    println!("{:?}", res.replace("[", "").replace("]", ""));
-    Ok(())
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OnInvalidMove {
+    Fail,
+    Warn,
 }
 
 #[derive(Debug)]
-struct Move(u32, u32, u32);
+struct Step {
+    taken: Vec<String>,
+    warning: Option<MoveError>,
+}
 
-impl Move {
-    fn execute(&self, placements: &mut HashMap<u32, Vec<String>>) -> Option<()> {
-        let Move(count, from, to) = self;
-        let mut to_move = placements.get_mut(from).and_then(|from_col| {
-            let count = (*count).min(from_col.len() as u32);
-            let vec = from_col.split_off(from_col.len() - count as usize);
-            Some(vec)
-        })?;
-        placements.get_mut(to).and_then(|vec_to| {
-            to_move.reverse();
-            vec_to.append(&mut to_move);
-            Some(())
-        })
+struct Replay<'a> {
+    simulator: &'a Simulator,
+    crane: &'a dyn Crane,
+    policy: OnInvalidMove,
+    stacks: Stacks,
+    steps: Vec<Step>,
+    // Stack id and number of the move that first left it empty
+    emptied: Vec<(u32, usize)>,
+}
+
+impl<'a> Replay<'a> {
+    fn new(simulator: &'a Simulator, crane: &'a dyn Crane, policy: OnInvalidMove) -> Self {
+        Replay {
+            simulator,
+            crane,
+            policy,
+            stacks: simulator.stacks.clone(),
+            steps: vec![],
+            emptied: vec![],
+        }
     }
 
-    fn execute_9001(&self, placements: &mut HashMap<u32, Vec<String>>) -> Option<()> {
-        let Move(count, from, to) = self;
-        let mut to_move = placements.get_mut(from).and_then(|from_col| {
-            let count = (*count).min(from_col.len() as u32);
-            let vec = from_col.drain(from_col.len() - count as usize..).collect();
-            Some(vec)
-        })?;
-        placements.get_mut(to).and_then(|vec_to| {
-            vec_to.append(&mut to_move);
-            Some(())
+    // Number of moves applied so far
+    fn position(&self) -> usize {
+        self.steps.len()
+    }
+
+    fn describe(&self, number: usize) -> String {
+        let Move(count, from, to) = self.simulator.moves[number - 1];
+        format!("move {number} (move {count} from {from} to {to})")
+    }
+
+    fn warnings(&self) -> impl Iterator<Item = String> + '_ {
+        self.steps.iter().enumerate().filter_map(|(index, step)| {
+            step.warning
+                .as_ref()
+                .map(|warning| format!("{} : {warning}", self.describe(index + 1)))
         })
     }
+
+    // Returns `false` once every move has been applied
+    fn step_forward(&mut self) -> Result<bool, String> {
+        let Some(mov) = self.simulator.moves.get(self.position()) else {
+            return Ok(false);
+        };
+        let number = self.position() + 1;
+        let warning = self.stacks.check(mov).err();
+        if let (Some(err), OnInvalidMove::Fail) = (&warning, self.policy) {
+            return Err(format!("{} : {err}", self.describe(number)));
+        }
+        let was_empty = self.stacks.get(mov.1).is_some_and(|stack| stack.is_empty());
+        let taken = self.stacks.apply(mov, self.crane).unwrap_or_default();
+        let is_empty = self.stacks.get(mov.1).is_some_and(|stack| stack.is_empty());
+        if !was_empty && is_empty && !self.emptied.iter().any(|(id, _)| *id == mov.1) {
+            self.emptied.push((mov.1, number));
+        }
+        self.steps.push(Step { taken, warning });
+        Ok(true)
+    }
+
+    // Returns `false` when already back at the initial drawing
+    fn step_back(&mut self) -> bool {
+        let Some(step) = self.steps.pop() else {
+            return false;
+        };
+        let number = self.position() + 1;
+        self.stacks
+            .undo(&self.simulator.moves[number - 1], step.taken);
+        self.emptied.retain(|(_, emptied_at)| *emptied_at < number);
+        true
+    }
+
+    fn jump_to(&mut self, number: usize) -> Result<(), String> {
+        if number > self.simulator.moves.len() {
+            return Err(format!(
+                "There are only {} moves",
+                self.simulator.moves.len()
+            ));
+        }
+        while self.position() > number {
+            self.step_back();
+        }
+        while self.position() < number {
+            self.step_forward()?;
+        }
+        Ok(())
+    }
+}
+
+fn read_simulator() -> Result<Simulator, String> {
+    // let input = SAMPLE;
+    let input = fs::read_to_string("src/day5.input").map_err(|err| err.to_string())?;
+    input.parse::<Simulator>()
+}
+
+pub fn run_part_one() -> Result<String, String> {
+    Ok(read_simulator()?.run(&CrateMover9000)?.top_crates())
+}
+
+pub fn run_part_two() -> Result<String, String> {
+    Ok(read_simulator()?.run(&CrateMover9001)?.top_crates())
+}
+
+// Accepts `--crane <9000 | 9001 | capacity:K | chunk:K>`, which can be repeated, along with
+// `--warn` to keep going after an invalid move, `--show` to print the final drawing,
+// `--replay` to print the drawing after every move and `--step N` to stop after move N
+pub fn run_command(args: &[String]) -> Result<(), String> {
+    let simulator = read_simulator()?;
+    let mut cranes: Vec<Box<dyn Crane>> = vec![];
+    let (mut show, mut replay, mut step) = (false, false, None);
+    let mut policy = OnInvalidMove::Fail;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--show" => show = true,
+            "--replay" => replay = true,
+            "--warn" => policy = OnInvalidMove::Warn,
+            "--step" => {
+                step = Some(
+                    args.next()
+                        .ok_or("--step expects a value")?
+                        .parse::<usize>()
+                        .map_err(|err| format!("Bad move number : {err}"))?,
+                )
+            }
+            "--crane" => cranes.push(
+                args.next()
+                    .ok_or("--crane expects a value")?
+                    .parse::<Box<dyn Crane>>()?,
+            ),
+            other => return Err(format!("Unknown argument {:?}", other)),
+        }
+    }
+    if cranes.is_empty() {
+        cranes.push(Box::new(CrateMover9000));
+    }
+    for crane in cranes.iter() {
+        let mut run = Replay::new(&simulator, crane.as_ref(), policy);
+        println!("{}", crane.name());
+        if replay {
+            println!("{}\n", run.stacks);
+            while run.position() < step.unwrap_or(simulator.moves.len()) && run.step_forward()? {
+                println!("{}\n{}\n", run.describe(run.position()), run.stacks);
+            }
+        } else {
+            run.jump_to(step.unwrap_or(simulator.moves.len()))?;
+            if show {
+                println!("{}\n", run.stacks);
+            }
+        }
+        run.warnings()
+            .for_each(|warning| println!("warning : {warning}"));
+        run.emptied.iter().for_each(|(id, number)| {
+            println!("stack {id} first emptied by {}", run.describe(*number))
+        });
+        println!(
+            "top crates after {} moves : {}",
+            run.position(),
+            run.stacks.top_crates()
+        );
+    }
+    Ok(())
 }
 
+#[derive(Debug)]
+struct Move(u32, u32, u32);
+
 impl FromStr for Move {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let x = s
-            .split(" ")
-            .filter(|item| {
-                !item.is_empty() && !item.contains(|character: char| character.is_alphabetic())
-            })
-            .map(|character| {
-                character
-                    .parse::<u32>()
-                    .map_err(|err| format!("Badly formatted move : {}", err.to_string()))
-            })
-            .collect::<Result<Vec<u32>, String>>()?;
-        Ok(Move(x[0], x[1], x[2]))
+        match s.split_whitespace().collect::<Vec<&str>>()[..] {
+            ["move", count, "from", from, "to", to] => {
+                let number = |value: &str| {
+                    value
+                        .parse::<u32>()
+                        .map_err(|err| format!("Badly formatted move {:?} : {}", s, err))
+                };
+                Ok(Move(number(count)?, number(from)?, number(to)?))
+            }
+            _ => Err(format!("Badly formatted move : {:?}", s)),
+        }
     }
 }
 
-fn parse_line(line: &str) -> Vec<Option<String>> {
-    let mut index = 0;
-    let mut blocks: Vec<Option<String>> = vec![];
-    while index < line.len() {
-        let iter = &line[index..=index + 2];
-        index += 4;
-        if iter.trim().len() == 0 {
-            blocks.push(None)
-        } else {
-            blocks.push(Some(iter.to_string()))
+// Byte span and text of every whitespace separated token of a line
+fn tokens(line: &str) -> Vec<(usize, usize, &str)> {
+    let mut tokens = vec![];
+    let mut start = None;
+    for (index, character) in line.char_indices().chain([(line.len(), ' ')]) {
+        match (start, character.is_whitespace()) {
+            (None, false) => start = Some(index),
+            (Some(begin), true) => {
+                tokens.push((begin, index, &line[begin..index]));
+                start = None;
+            }
+            _ => (),
+        }
+    }
+    tokens
+}
+
+// Columns are located through the stack numbers of the footer: a crate belongs to the
+// stack whose number sits under it
+fn parse_drawing(drawing: &str) -> Result<Stacks, String> {
+    let mut rows = drawing.lines().collect::<Vec<&str>>();
+    let footer = rows.pop().ok_or("Empty drawing")?;
+    let columns = tokens(footer);
+    let mut stacks = columns
+        .iter()
+        .map(|(_, _, id)| {
+            id.parse::<u32>()
+                .map(|id| Stack { id, crates: vec![] })
+                .map_err(|err| {
+                    format!(
+                        "line {} : bad stack number {:?} : {}",
+                        rows.len() + 1,
+                        id,
+                        err
+                    )
+                })
+        })
+        .collect::<Result<Vec<Stack>, String>>()?;
+    if let Some(stack) = stacks
+        .iter()
+        .enumerate()
+        .find(|(index, stack)| stacks[..*index].iter().any(|other| other.id == stack.id))
+        .map(|(_, stack)| stack)
+    {
+        return Err(format!(
+            "line {} : duplicate stack number {}",
+            rows.len() + 1,
+            stack.id
+        ));
+    }
+    for (depth, (index, row)) in rows.iter().enumerate().rev().enumerate() {
+        let line = index + 1;
+        for (start, end, cell) in tokens(row) {
+            let label = cell
+                .strip_prefix('[')
+                .and_then(|cell| cell.strip_suffix(']'))
+                .filter(|label| !label.is_empty())
+                .ok_or(format!("line {line} : badly formatted crate {:?}", cell))?;
+            let column = columns
+                .iter()
+                .position(|(column_start, column_end, _)| {
+                    *column_start < end && start < *column_end
+                })
+                .ok_or(format!(
+                    "line {line} : crate {cell} is not above any stack number"
+                ))?;
+            let stack = &mut stacks[column];
+            match stack.crates.len().cmp(&depth) {
+                std::cmp::Ordering::Equal => stack.crates.push(label.to_string()),
+                std::cmp::Ordering::Less => {
+                    return Err(format!(
+                        "line {line} : crate {cell} floats above stack {}",
+                        stack.id
+                    ))
+                }
+                std::cmp::Ordering::Greater => {
+                    return Err(format!(
+                        "line {line} : several crates for stack {}",
+                        stack.id
+                    ))
+                }
+            }
         }
     }
-    blocks
+    Ok(Stacks(stacks))
 }
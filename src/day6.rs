@@ -1,38 +1,263 @@
-use std::fs;
-
-pub fn run_part_one() {
-    let count = fs::read_to_string("src/day6.input")
-        .unwrap()
-        .chars()
-        .take_while(check_marker(4))
-        .count();
-    println!("{}", count + 1)
-}
-
-pub fn run_part_two() {
-    let count = fs::read_to_string("src/day6.input")
-        .unwrap()
-        .chars()
-        .take_while(check_marker(14))
-        .count();
-    println!("{}", count + 1)
-}
-
-fn check_marker(marker_size: usize) -> Box<dyn FnMut(&char) -> bool> {
-    let mut buf = String::from("");
-    Box::new(move |character: &char| {
-        if buf.contains(*character) {
-            let character_index = buf.find(*character);
-            match character_index {
-                Some(index) => buf = format!("{}{}", &buf[index + 1..], *character),
+use std::{
+    fs::File,
+    io::{self, BufReader, Read},
+    ops::Range,
+};
+
+const PACKET_MARKER_SIZE: usize = 4;
+const MESSAGE_MARKER_SIZE: usize = 14;
+const READ_CHUNK_SIZE: usize = 1 << 16;
+
+#[allow(unused)]
+const SAMPLE: &str = "mjqjpqmgbljsphdztnvjfqwrcgsmlb";
+
+// Tracks the last position of every byte value, so the start of the current run of
+// distinct bytes only ever moves forward
+struct MarkerDetector {
+    size: usize,
+    // 1-based position of the last occurrence of each byte, 0 when never seen
+    last_seen: [usize; 256],
+    // Number of bytes before the current run of distinct bytes
+    run_start: usize,
+    position: usize,
+}
+
+impl MarkerDetector {
+    fn new(size: usize) -> Self {
+        MarkerDetector {
+            size,
+            last_seen: [0; 256],
+            run_start: 0,
+            position: 0,
+        }
+    }
+
+    // Returns `true` when the last `size` bytes fed are all different
+    fn push(&mut self, byte: u8) -> bool {
+        self.position += 1;
+        let seen = &mut self.last_seen[byte as usize];
+        self.run_start = self.run_start.max(*seen);
+        *seen = self.position;
+        self.position - self.run_start >= self.size
+    }
+
+    // Forgets the bytes fed so far, the next window starts with the next byte
+    fn restart(&mut self) {
+        self.run_start = self.position;
+    }
+}
+
+struct Markers<I> {
+    bytes: I,
+    detector: MarkerDetector,
+    non_overlapping: bool,
+}
+
+impl<I> Markers<I> {
+    // Only yields markers whose window starts after the previous marker
+    fn non_overlapping(mut self) -> Self {
+        self.non_overlapping = true;
+        self
+    }
+}
+
+// Yields the 1-based position of every byte that ends a window of `size` distinct bytes
+trait MarkerExt: Iterator<Item = io::Result<u8>> + Sized {
+    fn markers(self, size: usize) -> Markers<Self> {
+        Markers {
+            bytes: self,
+            detector: MarkerDetector::new(size),
+            non_overlapping: false,
+        }
+    }
+
+    fn frames(self) -> Frames<Self> {
+        Frames {
+            bytes: self,
+            packet: MarkerDetector::new(PACKET_MARKER_SIZE),
+            message: MarkerDetector::new(MESSAGE_MARKER_SIZE),
+            payload: None,
+        }
+    }
+}
+
+impl<I: Iterator<Item = io::Result<u8>>> MarkerExt for I {}
+
+impl<I: Iterator<Item = io::Result<u8>>> Iterator for Markers<I> {
+    type Item = io::Result<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for byte in self.bytes.by_ref() {
+            match byte {
+                Ok(byte) if self.detector.push(byte) => {
+                    if self.non_overlapping {
+                        self.detector.restart();
+                    }
+                    return Some(Ok(self.detector.position));
+                }
+                Ok(_) => (),
+                Err(err) => return Some(Err(err)),
+            }
+        }
+        None
+    }
+}
+
+// Offsets are 0-based byte positions in the datastream, ranges exclude their end
+#[derive(Debug, PartialEq)]
+struct Frame {
+    packet_marker: Range<usize>,
+    payload_offset: usize,
+    payload: Vec<u8>,
+    message_marker: Range<usize>,
+}
+
+impl std::fmt::Display for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "packet marker {:?}, payload {:?} ({} bytes) {:?}, message marker {:?}",
+            self.packet_marker,
+            self.payload_offset..self.payload_offset + self.payload.len(),
+            self.payload.len(),
+            String::from_utf8_lossy(&self.payload),
+            self.message_marker
+        )
+    }
+}
+
+// Yields the payload found between each start-of-packet marker and the following
+// start-of-message marker. A trailing packet without a message marker is dropped.
+struct Frames<I> {
+    bytes: I,
+    packet: MarkerDetector,
+    message: MarkerDetector,
+    // Start-of-packet marker and the bytes read since, while looking for a message marker
+    payload: Option<(Range<usize>, Vec<u8>)>,
+}
+
+impl<I: Iterator<Item = io::Result<u8>>> Iterator for Frames<I> {
+    type Item = io::Result<Frame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for byte in self.bytes.by_ref() {
+            let byte = match byte {
+                Ok(byte) => byte,
+                Err(err) => return Some(Err(err)),
+            };
+            let found_packet = self.packet.push(byte);
+            let found_message = self.message.push(byte);
+            match self.payload {
+                None if found_packet => {
+                    let end = self.packet.position;
+                    self.payload = Some((end - PACKET_MARKER_SIZE..end, vec![]));
+                    self.message.restart();
+                }
                 None => (),
+                Some((_, ref mut payload)) => {
+                    payload.push(byte);
+                    if found_message {
+                        let (packet_marker, mut payload) = self.payload.take().unwrap();
+                        payload.truncate(payload.len() - MESSAGE_MARKER_SIZE);
+                        let end = self.message.position;
+                        self.packet.restart();
+                        return Some(Ok(Frame {
+                            payload_offset: packet_marker.end,
+                            packet_marker,
+                            payload,
+                            message_marker: end - MESSAGE_MARKER_SIZE..end,
+                        }));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+fn stream(path: &str) -> Result<impl Iterator<Item = io::Result<u8>>, String> {
+    let file = File::open(path).map_err(|err| format!("{path} : {err}"))?;
+    Ok(BufReader::with_capacity(READ_CHUNK_SIZE, file).bytes())
+}
+
+fn first_marker(bytes: impl Iterator<Item = io::Result<u8>>, size: usize) -> Result<usize, String> {
+    bytes
+        .markers(size)
+        .next()
+        .ok_or(format!("No marker of size {size} found"))?
+        .map_err(|err| err.to_string())
+}
+
+pub fn run_part_one() -> Result<usize, String> {
+    // let input = SAMPLE.bytes().map(Ok);
+    first_marker(stream("src/day6.input")?, PACKET_MARKER_SIZE)
+}
+
+pub fn run_part_two() -> Result<usize, String> {
+    // let input = SAMPLE.bytes().map(Ok);
+    first_marker(stream("src/day6.input")?, MESSAGE_MARKER_SIZE)
+}
+
+// Accepts `[markers | frames] [--size N] [--non-overlapping] [path]`. Without a subcommand
+// the first marker of each size is printed, `markers` prints all of them and `frames`
+// decodes the payloads between start-of-packet and start-of-message markers.
+pub fn run_command(args: &[String]) -> Result<(), String> {
+    let mut path = "src/day6.input";
+    let mut sizes = vec![];
+    let mut command = None;
+    let mut non_overlapping = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--size" => sizes.push(
+                match args
+                    .next()
+                    .ok_or("--size expects a value")?
+                    .parse::<usize>()
+                    .map_err(|err| format!("Bad marker size : {err}"))?
+                {
+                    0 => return Err(String::from("Bad marker size : must be at least 1")),
+                    size => size,
+                },
+            ),
+            "--non-overlapping" => non_overlapping = true,
+            "markers" | "frames" if command.is_none() => command = Some(arg.as_str()),
+            other if other.starts_with('-') => return Err(format!("Unknown argument {:?}", other)),
+            other => path = other,
+        }
+    }
+    if sizes.is_empty() {
+        sizes = vec![PACKET_MARKER_SIZE, MESSAGE_MARKER_SIZE];
+    }
+    match command {
+        Some("frames") => {
+            for frame in stream(path)?.frames() {
+                println!("{}", frame.map_err(|err| err.to_string())?);
+            }
+        }
+        Some(_) => {
+            for size in sizes {
+                let markers = stream(path)?.markers(size);
+                let markers = match non_overlapping {
+                    true => markers.non_overlapping(),
+                    false => markers,
+                };
+                for marker in markers {
+                    println!(
+                        "marker of size {size} : {}",
+                        marker.map_err(|err| err.to_string())?
+                    );
+                }
             }
-        } else {
-            buf.push(*character);
-            if buf.len() >= marker_size {
-                return false;
+        }
+        None => {
+            for size in sizes {
+                println!(
+                    "marker of size {size} : {}",
+                    first_marker(stream(path)?, size)?
+                );
             }
-        };
-        true
-    })
+        }
+    }
+    Ok(())
 }
@@ -1,17 +1,32 @@
-use std::{cell::RefCell, collections::HashMap, fs, path::PathBuf, rc::Rc};
+mod disk;
+mod filesystem;
+mod json;
+mod planner;
+mod query;
+
+use std::{collections::HashSet, fs, path::Path};
 
 use nom::{
     branch::alt,
-    bytes::complete::{is_a, tag},
-    character::complete::{alphanumeric1, digit1, multispace1, newline, u32},
-    combinator::opt,
-    multi::{separated_list0, separated_list1},
+    bytes::complete::tag,
+    character::complete::{not_line_ending, u64},
+    combinator::{all_consuming, map, verify},
+    sequence::{preceded, separated_pair},
     IResult,
 };
 
-const MAX_SIZE: u32 = 100000;
-const MAX_DISK_SPACE: u32 = 70_000_000;
-const DISK_SPACE_NEEDED: u32 = 30_000_000;
+use filesystem::{FileSystem, FsError, FsPath, NodeId, NodeKind};
+use planner::{Candidates, Disk, Objective};
+use query::{Filter, Query};
+
+const MAX_SIZE: u64 = 100000;
+const MAX_DISK_SPACE: u64 = 70_000_000;
+const DISK_SPACE_NEEDED: u64 = 30_000_000;
+const DISK: Disk = Disk {
+    size: MAX_DISK_SPACE,
+    required: DISK_SPACE_NEEDED,
+};
+const DEFAULT_LARGEST: usize = 10;
 
 #[allow(unused)]
 const SAMPLE: &str = "$ cd /
@@ -38,318 +53,453 @@ $ ls
 5626152 d.ext
 7214296 k";
 
-pub fn run_part_one() {
-    let input = fs::read_to_string("src/day7.input").unwrap();
-    // let input = SAMPLE;
-    let (_, commands) = parse_commands(&input).unwrap();
-    let x = DirEntry::Dir(DirectoryEntry {
-        path: "/".into(),
-        size: None,
-        entries: vec![],
-        parent: None,
-    });
-    let root = Rc::new(RefCell::new(x));
-    let mut iterator = root.clone();
-    commands
-        .into_iter()
-        .for_each(|command| iterator = command.execute(iterator.clone()));
-    let mut sizes: Vec<u32> = vec![];
-    root.borrow_mut().get_size(&mut sizes);
-    let cumulated_sizes = sizes
-        .iter()
-        .filter(|size| **size < MAX_SIZE)
-        .fold(0, |mut acc, size| {
-            acc += size;
-            acc
-        });
-    println!();
-    println!("{}", cumulated_sizes);
+fn read_input() -> Result<String, String> {
+    fs::read_to_string("src/day7.input").map_err(|err| err.to_string())
 }
 
-pub fn run_part_two() {
-    let input = fs::read_to_string("src/day7.input").unwrap();
+fn read_filesystem() -> Result<FileSystem, String> {
     // let input = SAMPLE;
-    let (_, commands) = parse_commands(&input).unwrap();
-    let x = DirEntry::Dir(DirectoryEntry {
-        path: "/".into(),
-        size: None,
-        entries: vec![],
-        parent: None,
-    });
-    let root = Rc::new(RefCell::new(x));
-    let mut iterator = root.clone();
-    commands
-        .into_iter()
-        .for_each(|command| iterator = command.execute(iterator.clone()));
-    let mut sizes: Vec<u32> = vec![];
-    root.borrow_mut().get_size(&mut sizes);
-    println!("--------------------------------------------------");
-    let root_borrow = root.borrow();
-    let to_delete = root_borrow.size().map(|rootdir_size| {
-        let min_size = DISK_SPACE_NEEDED - (MAX_DISK_SPACE - rootdir_size);
-        let to_delete = sizes.iter().fold(None, |acc: Option<u32>, val| match acc {
-            Some(ref curr_val) => {
-                if *val < *curr_val && *val > min_size {
-                    Some(*val)
-                } else {
-                    acc
-                }
-            }
-            None => {
-                if *val > min_size {
-                    Some(*val)
-                } else {
-                    None
-                }
-            }
-        });
-        to_delete.unwrap()
-    });
-    println!("Directory to erase is : {:?}", to_delete);
-    println!("--------------------------------------------------");
+    Ok(build_filesystem(parse_commands(&read_input()?)?, MissingDirs::Reject)?.filesystem)
+}
+
+// What a `cd` into a directory that no `ls` mentioned does. Shell logs often `cd` straight
+// into a deep path, creating the directories lets them be read, at the cost of turning a
+// mistyped name into a new directory.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MissingDirs {
+    Reject,
+    Create,
 }
 
-enum DirEntry {
-    Dir(DirectoryEntry),
-    File(FileEntry),
+// A listing contradicting an earlier `ls` of the same directory. The first listing wins.
+#[derive(Debug, PartialEq)]
+enum Conflict {
+    SizeChanged {
+        path: FsPath,
+        before: u64,
+        after: u64,
+    },
+    KindChanged(FsPath),
+    Appeared(FsPath),
+    Disappeared(FsPath),
 }
 
-impl DirEntry {
-    fn size(&self) -> Option<&u32> {
+impl std::fmt::Display for Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            DirEntry::Dir(ref dir) => dir.size.as_ref(),
-            DirEntry::File(ref file) => Some(&file.size),
+            Conflict::SizeChanged {
+                path,
+                before,
+                after,
+            } => write!(f, "{path} : size changed from {before} to {after}"),
+            Conflict::KindChanged(path) => {
+                write!(f, "{path} : listed both as a file and a directory")
+            }
+            Conflict::Appeared(path) => write!(f, "{path} : missing from an earlier listing"),
+            Conflict::Disappeared(path) => write!(f, "{path} : missing from this listing"),
         }
     }
 }
 
-#[derive(Default, Debug)]
-struct DirectoryEntry {
-    path: PathBuf,
-    pub size: Option<u32>,
-    entries: Vec<Rc<RefCell<DirEntry>>>,
-    parent: Option<Rc<RefCell<DirEntry>>>,
-}
-
 #[derive(Debug)]
-struct FileEntry {
-    path: PathBuf,
-    size: u32,
-    parent: Option<Rc<RefCell<DirEntry>>>,
+struct Reconstruction {
+    filesystem: FileSystem,
+    conflicts: Vec<(usize, Conflict)>,
+    // Directories entered but never listed, their sizes may be too small
+    unlisted: Vec<NodeId>,
 }
 
-impl Default for FileEntry {
-    fn default() -> Self {
-        FileEntry {
-            path: PathBuf::default(),
-            size: u32::default(),
-            parent: None,
-        }
+fn build_filesystem(
+    commands: Vec<(usize, Command)>,
+    missing: MissingDirs,
+) -> Result<Reconstruction, String> {
+    let mut filesystem = FileSystem::new();
+    let mut cwd = filesystem.root();
+    let mut entered = vec![];
+    let mut listed = HashSet::new();
+    let mut conflicts = vec![];
+    for (line, command) in commands {
+        let result = match command {
+            Command::Cd(path) => {
+                change_dir(&mut filesystem, cwd, path, missing, &mut entered).map(|dir| {
+                    cwd = dir;
+                    entered.push(dir);
+                })
+            }
+            Command::Ls(entries) => {
+                let relisting = !listed.insert(cwd);
+                merge_listing(&mut filesystem, cwd, entries, relisting).map(|found| {
+                    conflicts.extend(found.into_iter().map(|conflict| (line, conflict)))
+                })
+            }
+        };
+        result.map_err(|err| format!("line {line} : {err}"))?;
     }
+    let mut seen = HashSet::new();
+    entered.retain(|dir| !listed.contains(dir) && seen.insert(*dir));
+    Ok(Reconstruction {
+        filesystem,
+        conflicts,
+        unlisted: entered,
+    })
 }
 
-trait GetSize {
-    fn get_size(&mut self, sizes: &mut Vec<u32>) -> u32;
+fn dir_sizes(filesystem: &FileSystem) -> Vec<u64> {
+    let sizes = filesystem.sizes();
+    filesystem
+        .ids()
+        .filter(|id| filesystem.is_dir(*id))
+        .map(|id| sizes[id.index()])
+        .collect()
 }
 
-impl GetSize for FileEntry {
-    fn get_size(&mut self, sizes: &mut Vec<u32>) -> u32 {
-        self.size
-    }
-}
-
-impl GetSize for DirectoryEntry {
-    fn get_size(&mut self, sizes: &mut Vec<u32>) -> u32 {
-        let x = match self.size {
-            Some(ref size) => return *size,
-            None => {
-                let size = self
-                    .entries
-                    .iter_mut()
-                    .map(|entry| entry.borrow_mut().get_size(sizes))
-                    .fold(0, |mut acc, size| {
-                        acc += size;
-                        acc
-                    });
-                sizes.push(size);
-                self.size = Some(size);
-                size
-            }
-        };
-        x
-    }
+pub fn run_part_one() -> Result<u64, String> {
+    Ok(dir_sizes(&read_filesystem()?)
+        .into_iter()
+        .filter(|size| *size < MAX_SIZE)
+        .sum())
 }
 
-impl GetSize for DirEntry {
-    fn get_size(&mut self, sizes: &mut Vec<u32>) -> u32 {
-        match self {
-            DirEntry::Dir(directory) => directory.get_size(sizes),
-            DirEntry::File(file) => file.get_size(sizes),
-        }
-    }
+pub fn run_part_two() -> Result<u64, String> {
+    let filesystem = read_filesystem()?;
+    let sizes = filesystem.sizes();
+    planner::single_directory(
+        &filesystem,
+        &sizes,
+        DISK.to_free(sizes[filesystem.root().index()]),
+    )
+    .map(|id| sizes[id.index()])
+    .ok_or(String::from("No directory is big enough"))
 }
 
 #[derive(Debug)]
 enum FileType<'a> {
-    File { size: u32, name: &'a str },
+    File { size: u64, name: &'a str },
     Dir { name: &'a str },
 }
 
-impl<'a> FileType<'a> {
-    fn is_dir(&self) -> bool {
-        match self {
-            FileType::File { size, name } => false,
-            _ => true,
-        }
-    }
-}
-
-struct FromWrapper(Vec<Rc<RefCell<DirEntry>>>);
-
-impl<'a> From<(Rc<RefCell<DirEntry>>, Vec<FileType<'a>>)> for FromWrapper {
-    fn from(value: (Rc<RefCell<DirEntry>>, Vec<FileType<'a>>)) -> Self {
-        let res = value
-            .1
-            .into_iter()
-            .map(|file| match file {
-                FileType::File { size, name } => Rc::new(RefCell::new(DirEntry::File(FileEntry {
-                    path: name.into(),
-                    size,
-                    parent: Some(value.0.clone()),
-                }))),
-                FileType::Dir { name } => Rc::new(RefCell::new(DirEntry::Dir(DirectoryEntry {
-                    path: name.into(),
-                    size: None,
-                    entries: vec![],
-                    parent: Some(value.0.clone()),
-                }))),
-            })
-            .collect::<Vec<Rc<RefCell<DirEntry>>>>();
-        FromWrapper(res)
-    }
-}
-
 #[derive(Debug)]
 enum Command<'a> {
-    Cd(CD<'a>),
+    Cd(CdPath<'a>),
     Ls(Vec<FileType<'a>>),
 }
 
-#[derive(Debug)]
-enum CD<'a> {
-    Root,
-    Back,
-    Forward(&'a str),
+// Target of a `cd`, segments may be `..` or `.`
+#[derive(Debug, PartialEq)]
+struct CdPath<'a> {
+    absolute: bool,
+    segments: Vec<&'a str>,
 }
 
-impl<'a> Command<'a> {
-    fn execute(self, entry: Rc<RefCell<DirEntry>>) -> Rc<RefCell<DirEntry>> {
-        match self {
-            Command::Cd(command) => match command {
-                CD::Root => entry,
-                CD::Back => {
-                    let x = match *entry.borrow() {
-                        DirEntry::Dir(ref dir) => match &dir.parent {
-                            Some(parent) => parent.clone(),
-                            None => panic!("no parent!"),
-                        },
-                        _ => entry.clone(),
-                    };
-                    x
-                }
-                CD::Forward(dir_name) => match *entry.borrow() {
-                    DirEntry::Dir(ref dir) => dir
-                        .entries
-                        .iter()
-                        .find(|file| match *file.borrow() {
-                            DirEntry::Dir(ref dir) => {
-                                dir.path.file_name().unwrap().to_string_lossy().to_string()
-                                    == dir_name
-                            }
-                            DirEntry::File(_) => false,
-                        })
-                        .expect("file not found")
-                        .clone(),
-                    _ => panic!("Cd forward on file"),
-                },
-            },
-            Command::Ls(entries) => {
-                let FromWrapper(mut res) = FromWrapper::from((entry.clone(), entries));
-                let mut borrowed_entry = entry.borrow_mut();
-                match *borrowed_entry {
-                    DirEntry::Dir(ref mut dir) => {
-                        dir.entries.append(&mut res);
-                        drop(borrowed_entry);
-                        return entry;
+// Every directory walked through is added to `entered`, so that the ones created on the way
+// are reported when never listed
+fn change_dir(
+    filesystem: &mut FileSystem,
+    cwd: NodeId,
+    path: CdPath,
+    missing: MissingDirs,
+    entered: &mut Vec<NodeId>,
+) -> Result<NodeId, FsError> {
+    let start = match path.absolute {
+        true => filesystem.root(),
+        false => cwd,
+    };
+    path.segments
+        .into_iter()
+        .try_fold(start, |dir, segment| match segment {
+            "." => Ok(dir),
+            ".." => filesystem.node(dir).parent.ok_or(FsError::AboveRoot),
+            name => {
+                let child = match (filesystem.child(dir, name), missing) {
+                    (Some(child), _) if !filesystem.is_dir(child) => {
+                        return Err(FsError::NotADirectory(filesystem.path(child)))
+                    }
+                    (Some(child), _) => child,
+                    (None, MissingDirs::Reject) => {
+                        return Err(FsError::NotFound(filesystem.path(dir).join(name)))
                     }
-                    DirEntry::File(_) => todo!(),
+                    (None, MissingDirs::Create) => filesystem.add_dir(dir, name)?,
+                };
+                entered.push(child);
+                Ok(child)
+            }
+        })
+}
+
+// Adds the entries missing from `dir`, entries already known are left untouched. When `dir`
+// was listed before, any difference with the earlier listing is returned as a conflict.
+fn merge_listing(
+    filesystem: &mut FileSystem,
+    dir: NodeId,
+    entries: Vec<FileType>,
+    relisting: bool,
+) -> Result<Vec<Conflict>, FsError> {
+    let mut conflicts = vec![];
+    let mut names = HashSet::new();
+    for entry in entries {
+        let (name, size) = match entry {
+            FileType::File { size, name } => (name, Some(size)),
+            FileType::Dir { name } => (name, None),
+        };
+        names.insert(name);
+        let existing = match filesystem.child(dir, name) {
+            Some(existing) => existing,
+            None => {
+                let id = match size {
+                    Some(size) => filesystem.add_file(dir, name, size)?,
+                    None => filesystem.add_dir(dir, name)?,
+                };
+                if relisting {
+                    conflicts.push(Conflict::Appeared(filesystem.path(id)));
                 }
+                continue;
+            }
+        };
+        let path = || filesystem.path(existing);
+        match (&filesystem.node(existing).kind, size) {
+            (NodeKind::File(before), Some(after)) if *before != after => {
+                conflicts.push(Conflict::SizeChanged {
+                    path: path(),
+                    before: *before,
+                    after,
+                })
             }
+            (NodeKind::File(_), None) | (NodeKind::Dir(_), Some(_)) => {
+                conflicts.push(Conflict::KindChanged(path()))
+            }
+            _ => (),
         }
     }
+    if relisting {
+        conflicts.extend(
+            filesystem
+                .children(dir)
+                .iter()
+                .filter(|child| !names.contains(filesystem.node(**child).name.as_str()))
+                .map(|child| Conflict::Disappeared(filesystem.path(*child))),
+        );
+    }
+    Ok(conflicts)
 }
 
-fn parse_commands(input: &str) -> IResult<&str, Vec<Command>> {
-    separated_list1(newline, parse_command)(input)
+// Returns every command along with its line number. Listing lines belong to the
+// `ls` right above them.
+fn parse_commands(input: &str) -> Result<Vec<(usize, Command<'_>)>, String> {
+    let mut commands: Vec<(usize, Command)> = vec![];
+    for (index, line) in input.lines().enumerate() {
+        let line_number = index + 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if line.starts_with('$') {
+            let (_, command) = all_consuming(alt((parse_ls, parse_cd)))(line)
+                .map_err(|_| format!("line {line_number} : unknown command {:?}", line))?;
+            commands.push((line_number, command));
+            continue;
+        }
+        let (_, entry) = all_consuming(parse_file_type)(line)
+            .map_err(|_| format!("line {line_number} : badly formatted entry {:?}", line))?;
+        let name = match entry {
+            FileType::File { name, .. } | FileType::Dir { name } => name,
+        };
+        if !filesystem::is_valid_name(name) {
+            return Err(format!(
+                "line {line_number} : invalid entry name {:?}",
+                name
+            ));
+        }
+        match commands.last_mut() {
+            Some((_, Command::Ls(entries))) => entries.push(entry),
+            _ => {
+                return Err(format!(
+                    "line {line_number} : entry outside of an ls output"
+                ))
+            }
+        }
+    }
+    Ok(commands)
 }
 
-fn parse_command(input: &str) -> IResult<&str, Command> {
-    nom::branch::alt((parse_ls, parse_cd))(input)
+fn parse_name(input: &str) -> IResult<&str, &str> {
+    verify(not_line_ending, |name: &str| !name.is_empty())(input)
 }
 
-fn parse_ls(input: &str) -> IResult<&str, Command> {
-    let (input, _) = tag("$ ls")(input)?;
-    let (input, _) = newline(input)?;
-    let (input, vec) = separated_list0(newline, parse_file_type)(input)?;
-    Ok((input, Command::Ls(vec)))
+fn parse_ls(input: &str) -> IResult<&str, Command<'_>> {
+    map(tag("$ ls"), |_| Command::Ls(vec![]))(input)
 }
 
-fn parse_cd(input: &str) -> IResult<&str, Command> {
-    use Command::Cd;
-    let (input, _) = tag("$ cd ")(input)?;
-    let (input, name) = alt((tag(".."), alphanumeric1, tag("/")))(input)?;
-    let cd = match name {
-        "/" => Cd(CD::Root),
-        ".." => Cd(CD::Back),
-        other => Cd(CD::Forward(other)),
+fn parse_cd(input: &str) -> IResult<&str, Command<'_>> {
+    let (input, path) = preceded(tag("$ cd "), parse_name)(input)?;
+    let cd = CdPath {
+        absolute: path.starts_with('/'),
+        segments: path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect(),
     };
-    Ok((input, cd))
+    Ok((input, Command::Cd(cd)))
 }
 
-fn parse_file_type(input: &str) -> IResult<&str, FileType> {
-    let (input, size) = opt(digit1)(input)?;
-    let (input, file_type) = match size {
-        Some(size) => {
-            let (_, size) = u32(size)?;
-            let (input, _) = multispace1(input)?;
-            let (input, name) = is_a("abcdefghijklmnopqrstuvwxyz.")(input)?;
-            (input, FileType::File { size, name })
-        }
-        None => {
-            let (input, _) = tag("dir ")(input)?;
-            let (input, name) = alphanumeric1(input)?;
-            (input, FileType::Dir { name })
-        }
-    };
-    Ok((input, file_type))
+fn parse_file_type(input: &str) -> IResult<&str, FileType<'_>> {
+    alt((
+        map(preceded(tag("dir "), parse_name), |name| FileType::Dir {
+            name,
+        }),
+        map(separated_pair(u64, tag(" "), parse_name), |(size, name)| {
+            FileType::File { size, name }
+        }),
+    ))(input)
 }
 
-impl std::fmt::Debug for DirEntry {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            DirEntry::Dir(ref dir) => {
-                write!(
-                    f,
-                    "{{ \n  path: {:?}\n  size: {:?}\n  entries: {:?}\n}}",
-                    dir.path, dir.size, dir.entries
+fn read_snapshot(path: &str) -> Result<FileSystem, String> {
+    let input = fs::read_to_string(path).map_err(|err| format!("{path} : {err}"))?;
+    json::from_json(&json::parse(&input)?).map_err(|err| format!("{path} : {err}"))
+}
+
+fn print_check(reconstruction: &Reconstruction) {
+    for (line, conflict) in reconstruction.conflicts.iter() {
+        println!("line {line} : {conflict}");
+    }
+    for dir in reconstruction.unlisted.iter() {
+        println!(
+            "{} : never listed, its size is incomplete",
+            reconstruction.filesystem.path(*dir)
+        );
+    }
+    println!(
+        "{} conflict(s), {} unlisted director(ies)",
+        reconstruction.conflicts.len(),
+        reconstruction.unlisted.len()
+    );
+}
+
+// Accepts one of the following, reading the filesystem from a real directory or a JSON
+// snapshot instead of the transcript when given `--from-dir PATH` or `--from-json PATH`.
+// With `--create-missing`, a `cd` into a directory that was never listed creates it.
+//   check
+//   transcript
+//   json
+//   diff SNAPSHOT
+//   du [path] [--depth N]
+//   tree [path] [--depth N]
+//   find [path] [--name GLOB] [--size [+-]N] [--type f|d]
+//   largest [N] [--type f|d]
+//   extensions
+//   plan [--disk N] [--required N] [--minimize bytes|items] [--candidates dirs|files|any]
+// Without a subcommand both parts are run.
+pub fn run_command(args: &[String]) -> Result<(), String> {
+    let Some((command, args)) = args.split_first() else {
+        println!("day 7: {:?}", run_part_one());
+        println!("day 7: {:?}", run_part_two());
+        return Ok(());
+    };
+    let mut positional = None;
+    let mut depth = None;
+    let mut filter = Filter::default();
+    let mut disk = DISK;
+    let mut objective = Objective::FewestBytes;
+    let mut candidates = Candidates::Dirs;
+    let mut from_dir = None;
+    let mut from_json = None;
+    let mut missing = MissingDirs::Reject;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{arg} expects a value"));
+        match arg.as_str() {
+            "--depth" => {
+                depth = Some(
+                    value()?
+                        .parse::<usize>()
+                        .map_err(|err| format!("Bad depth : {err}"))?,
                 )
             }
-            DirEntry::File(ref file) => write!(
-                f,
-                "{{ \n  path: {:?}\n  size: {:?}\n}}",
-                file.path, file.size
-            ),
+            "--name" => filter.name = Some(value()?.parse()?),
+            "--size" => filter.size = Some(value()?.parse()?),
+            "--type" => filter.kind = Some(value()?.parse()?),
+            "--disk" => {
+                disk.size = value()?
+                    .parse::<u64>()
+                    .map_err(|err| format!("Bad disk size : {err}"))?
+            }
+            "--required" => {
+                disk.required = value()?
+                    .parse::<u64>()
+                    .map_err(|err| format!("Bad required space : {err}"))?
+            }
+            "--minimize" => objective = value()?.parse()?,
+            "--candidates" => candidates = value()?.parse()?,
+            "--from-dir" => from_dir = Some(value()?),
+            "--from-json" => from_json = Some(value()?),
+            "--create-missing" => missing = MissingDirs::Create,
+            other if positional.is_none() => positional = Some(other),
+            other => return Err(format!("Unknown argument {:?}", other)),
         }
     }
+    let reconstruction = match (from_dir, from_json) {
+        (Some(_), Some(_)) => return Err(String::from("Only one of --from-dir and --from-json")),
+        (Some(path), None) => Reconstruction {
+            filesystem: disk::scan(Path::new(path))?,
+            conflicts: vec![],
+            unlisted: vec![],
+        },
+        (None, Some(path)) => Reconstruction {
+            filesystem: read_snapshot(path)?,
+            conflicts: vec![],
+            unlisted: vec![],
+        },
+        (None, None) => build_filesystem(parse_commands(&read_input()?)?, missing)?,
+    };
+    let filesystem = &reconstruction.filesystem;
+    let query = Query::new(filesystem);
+    let start = || {
+        filesystem
+            .lookup(&positional.unwrap_or("/").parse()?)
+            .map_err(|err| err.to_string())
+    };
+    match command.as_str() {
+        "check" => print_check(&reconstruction),
+        "transcript" => print!("{}", disk::transcript(filesystem)),
+        "json" => println!("{:#}", json::to_json(filesystem)),
+        "diff" => {
+            let snapshot = read_snapshot(positional.ok_or("diff expects a snapshot")?)?;
+            for change in json::diff(&snapshot, filesystem) {
+                println!("{change}");
+            }
+        }
+        "du" => {
+            for (dir, size) in query.du(start()?, depth) {
+                println!("{size}\t{}", filesystem.path(dir));
+            }
+        }
+        "tree" => print!("{}", query.tree(start()?, depth)),
+        "find" => {
+            for id in query.find(start()?, &filter) {
+                println!("{}\t{}", query.size(id), filesystem.path(id));
+            }
+        }
+        "largest" => {
+            let count = positional
+                .map(|count| count.parse::<usize>())
+                .unwrap_or(Ok(DEFAULT_LARGEST))
+                .map_err(|err| format!("Bad count : {err}"))?;
+            for id in query.largest(count, filter.kind) {
+                println!("{}\t{}", query.size(id), filesystem.path(id));
+            }
+        }
+        "extensions" => {
+            for (extension, size, count) in query.extensions() {
+                let extension = match extension.is_empty() {
+                    true => String::from("(none)"),
+                    false => format!(".{extension}"),
+                };
+                println!("{size}\t{count} file(s)\t{extension}");
+            }
+        }
+        "plan" => print!(
+            "{}",
+            planner::plan(filesystem, disk, objective, candidates)?
+        ),
+        _ => return Err(format!("Unknown command {:?}", command)),
+    }
+    Ok(())
 }
-
@@ -0,0 +1,72 @@
+use std::{fs, path::Path};
+
+use super::filesystem::{FileSystem, NodeKind};
+
+// Builds the tree found under `path`. Entries are sorted by name and symbolic links are
+// skipped, so the result does not depend on the platform and cannot loop.
+pub fn scan(path: &Path) -> Result<FileSystem, String> {
+    let mut filesystem = FileSystem::new();
+    let mut pending = vec![(path.to_path_buf(), filesystem.root())];
+    while let Some((path, dir)) = pending.pop() {
+        let error = |err: std::io::Error| format!("{} : {err}", path.display());
+        let mut entries = fs::read_dir(&path)
+            .map_err(error)?
+            .collect::<Result<Vec<fs::DirEntry>, std::io::Error>>()
+            .map_err(error)?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let metadata = entry.metadata().map_err(error)?;
+            let result = match metadata.file_type() {
+                kind if kind.is_dir() => filesystem
+                    .add_dir(dir, &name)
+                    .map(|child| pending.push((entry.path(), child))),
+                kind if kind.is_file() => {
+                    filesystem.add_file(dir, &name, metadata.len()).map(|_| ())
+                }
+                _ => Ok(()),
+            };
+            result.map_err(|err| err.to_string())?;
+        }
+    }
+    Ok(filesystem)
+}
+
+// Writes the `cd` and `ls` commands that would have discovered the whole tree, going into
+// the directories in the order they are listed. Names never hold a `/` or a line break, so
+// every `cd` goes down exactly one level.
+pub fn transcript(filesystem: &FileSystem) -> String {
+    let mut lines = vec![String::from("$ cd /")];
+    // `None` marks the end of a directory, where the transcript goes back up
+    let mut pending = vec![Some(filesystem.root())];
+    while let Some(next) = pending.pop() {
+        let Some(dir) = next else {
+            lines.push(String::from("$ cd .."));
+            continue;
+        };
+        if dir != filesystem.root() {
+            lines.push(format!("$ cd {}", filesystem.node(dir).name));
+        }
+        lines.push(String::from("$ ls"));
+        for child in filesystem.children(dir) {
+            let node = filesystem.node(*child);
+            lines.push(match node.kind {
+                NodeKind::Dir(_) => format!("dir {}", node.name),
+                NodeKind::File(size) => format!("{size} {}", node.name),
+            });
+        }
+        if dir != filesystem.root() {
+            pending.push(None);
+        }
+        pending.extend(
+            filesystem
+                .children(dir)
+                .iter()
+                .rev()
+                .filter(|child| filesystem.is_dir(**child))
+                .map(|child| Some(*child)),
+        );
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
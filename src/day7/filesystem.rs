@@ -0,0 +1,219 @@
+use std::{fmt, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeKind {
+    Dir(Vec<NodeId>),
+    File(u64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub name: String,
+    pub parent: Option<NodeId>,
+    pub kind: NodeKind,
+}
+
+// Absolute path from the root, one segment per directory
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FsPath(Vec<String>);
+
+impl FsPath {
+    pub fn join(&self, name: &str) -> Self {
+        let mut segments = self.0.clone();
+        segments.push(name.to_string());
+        FsPath(segments)
+    }
+}
+
+// Paths are always resolved from the root, so the leading `/` is optional
+impl FromStr for FsPath {
+    type Err = String;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        Ok(FsPath(
+            path.split('/')
+                .filter(|segment| !segment.is_empty())
+                .map(String::from)
+                .collect(),
+        ))
+    }
+}
+
+impl fmt::Display for FsPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.is_empty() {
+            true => write!(f, "/"),
+            false => self
+                .0
+                .iter()
+                .try_for_each(|segment| write!(f, "/{segment}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FsError {
+    NotFound(FsPath),
+    NotADirectory(FsPath),
+    AlreadyExists(FsPath),
+    InvalidName { dir: FsPath, name: String },
+    AboveRoot,
+}
+
+impl fmt::Display for FsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FsError::NotFound(path) => write!(f, "{path} : no such file or directory"),
+            FsError::NotADirectory(path) => write!(f, "{path} : not a directory"),
+            FsError::AlreadyExists(path) => write!(f, "{path} : already exists"),
+            FsError::InvalidName { dir, name } => {
+                write!(f, "{dir} : invalid entry name {:?}", name)
+            }
+            FsError::AboveRoot => write!(f, "cannot go above the root directory"),
+        }
+    }
+}
+
+// Names that a `cd` would read as a path, e.g. `a/b` or `..`, cannot be stored
+pub fn is_valid_name(name: &str) -> bool {
+    !matches!(name, "" | "." | "..") && !name.contains(['/', '\n'])
+}
+
+// Nodes live in a single vector and refer to each other by index. A node is always
+// pushed after its parent, which `sizes` relies on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileSystem {
+    nodes: Vec<Node>,
+}
+
+impl Default for FileSystem {
+    fn default() -> Self {
+        FileSystem {
+            nodes: vec![Node {
+                name: String::new(),
+                parent: None,
+                kind: NodeKind::Dir(vec![]),
+            }],
+        }
+    }
+}
+
+impl FileSystem {
+    pub fn new() -> Self {
+        FileSystem::default()
+    }
+
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0]
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = NodeId> {
+        (0..self.nodes.len()).map(NodeId)
+    }
+
+    pub fn is_dir(&self, id: NodeId) -> bool {
+        matches!(self.node(id).kind, NodeKind::Dir(_))
+    }
+
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        match self.node(id).kind {
+            NodeKind::Dir(ref children) => children,
+            NodeKind::File(_) => &[],
+        }
+    }
+
+    pub fn child(&self, dir: NodeId, name: &str) -> Option<NodeId> {
+        self.children(dir)
+            .iter()
+            .copied()
+            .find(|child| self.node(*child).name == name)
+    }
+
+    pub fn path(&self, id: NodeId) -> FsPath {
+        let mut segments = vec![];
+        let mut current = id;
+        while let Some(parent) = self.node(current).parent {
+            segments.push(self.node(current).name.clone());
+            current = parent;
+        }
+        segments.reverse();
+        FsPath(segments)
+    }
+
+    pub fn lookup(&self, path: &FsPath) -> Result<NodeId, FsError> {
+        path.0.iter().try_fold(self.root(), |dir, name| {
+            self.child(dir, name)
+                .ok_or_else(|| FsError::NotFound(self.path(dir).join(name)))
+        })
+    }
+
+    fn add(&mut self, parent: NodeId, name: &str, kind: NodeKind) -> Result<NodeId, FsError> {
+        if !self.is_dir(parent) {
+            return Err(FsError::NotADirectory(self.path(parent)));
+        }
+        if !is_valid_name(name) {
+            return Err(FsError::InvalidName {
+                dir: self.path(parent),
+                name: name.to_string(),
+            });
+        }
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(Node {
+            name: name.to_string(),
+            parent: Some(parent),
+            kind,
+        });
+        if let NodeKind::Dir(ref mut children) = self.nodes[parent.0].kind {
+            children.push(id);
+        }
+        Ok(id)
+    }
+
+    // Returns the existing directory when there already is one with that name
+    pub fn add_dir(&mut self, parent: NodeId, name: &str) -> Result<NodeId, FsError> {
+        match self.child(parent, name) {
+            Some(existing) if self.is_dir(existing) => Ok(existing),
+            Some(existing) => Err(FsError::AlreadyExists(self.path(existing))),
+            None => self.add(parent, name, NodeKind::Dir(vec![])),
+        }
+    }
+
+    pub fn add_file(&mut self, parent: NodeId, name: &str, size: u64) -> Result<NodeId, FsError> {
+        match self.child(parent, name) {
+            Some(existing) => Err(FsError::AlreadyExists(self.path(existing))),
+            None => self.add(parent, name, NodeKind::File(size)),
+        }
+    }
+
+    // Total size of every node, indexed by `NodeId::index`. Children always come after
+    // their parent, so walking the nodes backwards is a post-order traversal.
+    pub fn sizes(&self) -> Vec<u64> {
+        let mut sizes = self
+            .nodes
+            .iter()
+            .map(|node| match node.kind {
+                NodeKind::File(size) => size,
+                NodeKind::Dir(_) => 0,
+            })
+            .collect::<Vec<u64>>();
+        for (index, node) in self.nodes.iter().enumerate().rev() {
+            if let Some(parent) = node.parent {
+                sizes[parent.0] += sizes[index];
+            }
+        }
+        sizes
+    }
+}
@@ -0,0 +1,340 @@
+use std::{collections::BTreeMap, fmt};
+
+use nom::{
+    branch::alt,
+    bytes::complete::{escaped_transform, tag, take_while_m_n},
+    character::complete::{char, multispace0, none_of, u64},
+    combinator::{all_consuming, map, map_opt, opt, value},
+    multi::separated_list0,
+    sequence::{delimited, preceded, separated_pair},
+    IResult,
+};
+
+use super::filesystem::{FileSystem, FsPath, NodeId, NodeKind};
+
+// The subset of JSON snapshots need, numbers are unsigned integers
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(u64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn field(&self, key: &str) -> Result<&Json, String> {
+        match self {
+            Json::Object(fields) => fields
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value)
+                .ok_or(format!("missing field {:?}", key)),
+            _ => Err(String::from("expected an object")),
+        }
+    }
+
+    fn as_str(&self) -> Result<&str, String> {
+        match self {
+            Json::String(string) => Ok(string),
+            _ => Err(String::from("expected a string")),
+        }
+    }
+
+    fn as_u64(&self) -> Result<u64, String> {
+        match self {
+            Json::Number(number) => Ok(*number),
+            _ => Err(String::from("expected a number")),
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, string: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in string.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    write!(f, "\"")
+}
+
+impl Json {
+    fn write(&self, f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+        // `{:#}` spreads arrays and objects over several lines
+        let pretty = f.alternate();
+        let newline = |f: &mut fmt::Formatter<'_>, indent: usize| match pretty {
+            true => write!(f, "\n{:indent$}", "", indent = indent * 2),
+            false => Ok(()),
+        };
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(bool) => write!(f, "{bool}"),
+            Json::Number(number) => write!(f, "{number}"),
+            Json::String(string) => write_string(f, string),
+            Json::Array(items) if items.is_empty() => write!(f, "[]"),
+            Json::Array(items) => {
+                write!(f, "[")?;
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    newline(f, indent + 1)?;
+                    item.write(f, indent + 1)?;
+                }
+                newline(f, indent)?;
+                write!(f, "]")
+            }
+            Json::Object(fields) if fields.is_empty() => write!(f, "{{}}"),
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (index, (key, value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    newline(f, indent + 1)?;
+                    write_string(f, key)?;
+                    write!(f, ":{}", if pretty { " " } else { "" })?;
+                    value.write(f, indent + 1)?;
+                }
+                newline(f, indent)?;
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+fn parse_unicode(input: &str) -> IResult<&str, char> {
+    map_opt(
+        preceded(
+            char('u'),
+            take_while_m_n(4, 4, |c: char| c.is_ascii_hexdigit()),
+        ),
+        |hex| u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+    )(input)
+}
+
+fn parse_string(input: &str) -> IResult<&str, String> {
+    let escape = alt((
+        value('"', char('"')),
+        value('\\', char('\\')),
+        value('/', char('/')),
+        value('\n', char('n')),
+        value('\r', char('r')),
+        value('\t', char('t')),
+        value('\u{8}', char('b')),
+        value('\u{c}', char('f')),
+        parse_unicode,
+    ));
+    map(
+        delimited(
+            char('"'),
+            opt(escaped_transform(none_of("\"\\"), '\\', escape)),
+            char('"'),
+        ),
+        Option::unwrap_or_default,
+    )(input)
+}
+
+fn parse_value(input: &str) -> IResult<&str, Json> {
+    delimited(
+        multispace0,
+        alt((
+            value(Json::Null, tag("null")),
+            value(Json::Bool(true), tag("true")),
+            value(Json::Bool(false), tag("false")),
+            map(u64, Json::Number),
+            map(parse_string, Json::String),
+            map(
+                delimited(
+                    char('['),
+                    separated_list0(char(','), parse_value),
+                    preceded(multispace0, char(']')),
+                ),
+                Json::Array,
+            ),
+            map(
+                delimited(
+                    char('{'),
+                    separated_list0(
+                        char(','),
+                        separated_pair(
+                            delimited(multispace0, parse_string, multispace0),
+                            char(':'),
+                            parse_value,
+                        ),
+                    ),
+                    preceded(multispace0, char('}')),
+                ),
+                Json::Object,
+            ),
+        )),
+        multispace0,
+    )(input)
+}
+
+pub fn parse(input: &str) -> Result<Json, String> {
+    all_consuming(parse_value)(input)
+        .map(|(_, json)| json)
+        .map_err(|err| format!("Invalid JSON : {err}"))
+}
+
+// Every node becomes `{"name", "path", "type", "size"}`, directories also get their
+// `children` and have the total size of their content as `size`
+pub fn to_json(filesystem: &FileSystem) -> Json {
+    node_to_json(filesystem, &filesystem.sizes(), filesystem.root())
+}
+
+fn node_to_json(filesystem: &FileSystem, sizes: &[u64], id: NodeId) -> Json {
+    let node = filesystem.node(id);
+    let mut fields = vec![
+        (String::from("name"), Json::String(node.name.clone())),
+        (
+            String::from("path"),
+            Json::String(filesystem.path(id).to_string()),
+        ),
+    ];
+    let kind = match node.kind {
+        NodeKind::Dir(_) => "dir",
+        NodeKind::File(_) => "file",
+    };
+    fields.push((String::from("type"), Json::String(kind.to_string())));
+    fields.push((String::from("size"), Json::Number(sizes[id.index()])));
+    if let NodeKind::Dir(ref children) = node.kind {
+        let children = children
+            .iter()
+            .map(|child| node_to_json(filesystem, sizes, *child))
+            .collect();
+        fields.push((String::from("children"), Json::Array(children)));
+    }
+    Json::Object(fields)
+}
+
+// Rebuilds the tree from names, types and file sizes. Paths and directory sizes are only
+// checked against the rebuilt tree, so a snapshot edited by hand cannot be inconsistent.
+pub fn from_json(json: &Json) -> Result<FileSystem, String> {
+    let mut filesystem = FileSystem::new();
+    let mut pending = vec![(json, filesystem.root())];
+    let mut dirs = vec![];
+    while let Some((json, dir)) = pending.pop() {
+        dirs.push((json, dir));
+        let children = match json.field("children")? {
+            Json::Array(children) => children,
+            _ => {
+                return Err(format!(
+                    "{} : children must be an array",
+                    filesystem.path(dir)
+                ))
+            }
+        };
+        for child in children {
+            let name = child.field("name")?.as_str()?;
+            let result = match child.field("type")?.as_str()? {
+                "dir" => filesystem
+                    .add_dir(dir, name)
+                    .map(|id| pending.push((child, id))),
+                "file" => filesystem
+                    .add_file(dir, name, child.field("size")?.as_u64()?)
+                    .map(|_| ()),
+                other => return Err(format!("Unknown node type {:?}", other)),
+            };
+            result.map_err(|err| err.to_string())?;
+        }
+    }
+    let sizes = filesystem.sizes();
+    for (json, dir) in dirs {
+        let path = filesystem.path(dir);
+        if json.field("path")?.as_str()? != path.to_string() {
+            return Err(format!("{path} : recorded with another path"));
+        }
+        let size = json.field("size")?.as_u64()?;
+        if size != sizes[dir.index()] {
+            return Err(format!(
+                "{path} : recorded size {size} but its content adds up to {}",
+                sizes[dir.index()]
+            ));
+        }
+    }
+    Ok(filesystem)
+}
+
+// Size of a directory in two snapshots, `None` when it is missing from one of them
+#[derive(Debug, PartialEq)]
+pub struct Change {
+    pub path: FsPath,
+    pub before: Option<u64>,
+    pub after: Option<u64>,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.before, self.after) {
+            (Some(before), Some(after)) if after >= before => {
+                write!(
+                    f,
+                    "{} : grew by {} ({before} -> {after})",
+                    self.path,
+                    after - before
+                )
+            }
+            (Some(before), Some(after)) => {
+                write!(
+                    f,
+                    "{} : shrank by {} ({before} -> {after})",
+                    self.path,
+                    before - after
+                )
+            }
+            (None, Some(after)) => write!(f, "{} : added ({after})", self.path),
+            (Some(before), None) => write!(f, "{} : removed ({before})", self.path),
+            (None, None) => write!(f, "{} : unchanged", self.path),
+        }
+    }
+}
+
+fn dir_sizes(filesystem: &FileSystem) -> BTreeMap<FsPath, u64> {
+    let sizes = filesystem.sizes();
+    filesystem
+        .ids()
+        .filter(|id| filesystem.is_dir(*id))
+        .map(|id| (filesystem.path(id), sizes[id.index()]))
+        .collect()
+}
+
+// Directories whose size differs between two snapshots, biggest growth first
+pub fn diff(before: &FileSystem, after: &FileSystem) -> Vec<Change> {
+    let (before, mut after) = (dir_sizes(before), dir_sizes(after));
+    let mut changes = vec![];
+    for (path, size) in before {
+        let new_size = after.remove(&path);
+        if new_size != Some(size) {
+            changes.push(Change {
+                path,
+                before: Some(size),
+                after: new_size,
+            });
+        }
+    }
+    changes.extend(after.into_iter().map(|(path, size)| Change {
+        path,
+        before: None,
+        after: Some(size),
+    }));
+    changes.sort_by_key(|change| {
+        std::cmp::Reverse(change.after.unwrap_or(0) as i128 - change.before.unwrap_or(0) as i128)
+    });
+    changes
+}
@@ -0,0 +1,641 @@
+use std::{fmt, rc::Rc, str::FromStr};
+
+use super::filesystem::{FileSystem, FsPath, NodeId};
+
+// Above this many partial sums per directory, sums close to each other are merged into
+// `BUCKETS` buckets and the plan is no longer guaranteed to be optimal
+const THIN_ABOVE: usize = 1 << 9;
+const BUCKETS: u64 = 1 << 7;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Disk {
+    pub size: u64,
+    pub required: u64,
+}
+
+impl Disk {
+    // Bytes that must be deleted to get `required` bytes of free space
+    pub fn to_free(self, used: u64) -> u64 {
+        self.required.saturating_sub(self.size.saturating_sub(used))
+    }
+}
+
+// Ties are broken by the other quantity. With `FewestItems` that tie break is best effort:
+// a selection is dropped as soon as another one frees more bytes with as few items.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Objective {
+    FewestBytes,
+    FewestItems,
+}
+
+impl FromStr for Objective {
+    type Err = String;
+
+    fn from_str(objective: &str) -> Result<Self, Self::Err> {
+        match objective {
+            "bytes" => Ok(Objective::FewestBytes),
+            "items" => Ok(Objective::FewestItems),
+            _ => Err(format!("Unknown objective {:?}", objective)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Candidates {
+    Dirs,
+    Files,
+    Any,
+}
+
+impl FromStr for Candidates {
+    type Err = String;
+
+    fn from_str(candidates: &str) -> Result<Self, Self::Err> {
+        match candidates {
+            "dirs" => Ok(Candidates::Dirs),
+            "files" => Ok(Candidates::Files),
+            "any" => Ok(Candidates::Any),
+            _ => Err(format!("Unknown candidates {:?}", candidates)),
+        }
+    }
+}
+
+// Nodes picked so far, shared between the states built on top of each other
+#[derive(Debug)]
+enum Selection {
+    Empty,
+    Node(NodeId),
+    Both(Rc<Selection>, Rc<Selection>),
+}
+
+impl Selection {
+    // Selections get as deep as the number of nodes they hold, so they are not walked
+    // recursively
+    fn collect(&self, nodes: &mut Vec<NodeId>) {
+        let mut pending = vec![self];
+        while let Some(selection) = pending.pop() {
+            match selection {
+                Selection::Empty => (),
+                Selection::Node(id) => nodes.push(*id),
+                Selection::Both(first, second) => {
+                    pending.push(second);
+                    pending.push(first);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct State {
+    bytes: u64,
+    items: usize,
+    selection: Rc<Selection>,
+}
+
+impl State {
+    fn empty() -> Self {
+        State {
+            bytes: 0,
+            items: 0,
+            selection: Rc::new(Selection::Empty),
+        }
+    }
+
+    fn combine(&self, other: &State) -> State {
+        match (self.items, other.items) {
+            (_, 0) => return self.clone(),
+            (0, _) => return other.clone(),
+            _ => (),
+        }
+        State {
+            bytes: self.bytes + other.bytes,
+            items: self.items + other.items,
+            selection: Rc::new(Selection::Both(
+                self.selection.clone(),
+                other.selection.clone(),
+            )),
+        }
+    }
+}
+
+// Selections of non-nested nodes within a subtree. Selections freeing too little are kept
+// sorted by number of bytes freed, with the fewest items for every number. Adding nodes to a
+// selection that already frees enough only makes it worse, so only the best of those is kept.
+struct Frontier {
+    open: Vec<State>,
+    done: Option<State>,
+}
+
+// Selections at `first` and `second` in the two frontiers being merged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Combination {
+    bytes: u64,
+    items: usize,
+    first: usize,
+    second: usize,
+}
+
+struct Optimizer {
+    to_free: u64,
+    objective: Objective,
+    exact: bool,
+}
+
+impl Optimizer {
+    fn key(&self, bytes: u64, items: usize) -> (u64, u64) {
+        match self.objective {
+            Objective::FewestBytes => (bytes, items as u64),
+            Objective::FewestItems => (items as u64, bytes),
+        }
+    }
+
+    fn empty(&self) -> Frontier {
+        Frontier {
+            open: vec![State::empty()],
+            done: None,
+        }
+    }
+
+    // Whether a selection freeing enough with `bytes` and `items` beats the one in `frontier`
+    fn improves_done(&self, frontier: &Frontier, bytes: u64, items: usize) -> bool {
+        frontier
+            .done
+            .as_ref()
+            .is_none_or(|done| self.key(bytes, items) < self.key(done.bytes, done.items))
+    }
+
+    fn insert(&self, frontier: &mut Frontier, state: State) {
+        if state.bytes >= self.to_free {
+            if self.improves_done(frontier, state.bytes, state.items) {
+                frontier.done = Some(state);
+            }
+            return;
+        }
+        match frontier
+            .open
+            .binary_search_by_key(&state.bytes, |open| open.bytes)
+        {
+            Ok(index) if frontier.open[index].items <= state.items => (),
+            Ok(index) => frontier.open[index] = state,
+            Err(index) => frontier.open.insert(index, state),
+        }
+    }
+
+    // Combinations are only turned into selections once pruned and thinned
+    fn merge(&mut self, first: Frontier, second: Frontier) -> Frontier {
+        let mut merged = Frontier {
+            open: vec![],
+            done: None,
+        };
+        for state in first.done.into_iter().chain(second.done) {
+            self.insert(&mut merged, state);
+        }
+        let mut combinations = vec![];
+        for (i, a) in first.open.iter().enumerate() {
+            for (j, b) in second.open.iter().enumerate() {
+                let (bytes, items) = (a.bytes + b.bytes, a.items + b.items);
+                if bytes < self.to_free {
+                    combinations.push(Combination {
+                        bytes,
+                        items,
+                        first: i,
+                        second: j,
+                    });
+                    continue;
+                }
+                if self.improves_done(&merged, bytes, items) {
+                    merged.done = Some(a.combine(b));
+                }
+                // Bigger sums from `second` can only delete more bytes
+                if self.objective == Objective::FewestBytes {
+                    break;
+                }
+            }
+        }
+        combinations.sort_unstable();
+        combinations.dedup_by_key(|combination| combination.bytes);
+        self.prune(&mut combinations, merged.done.as_ref());
+        self.thin(&mut combinations);
+        merged.open = combinations
+            .iter()
+            .map(|combination| {
+                first.open[combination.first].combine(&second.open[combination.second])
+            })
+            .collect();
+        merged
+    }
+
+    // When minimizing items, a selection is useless once another one frees more bytes with
+    // as few items, or once a selection freeing enough has as few items
+    fn prune(&self, combinations: &mut Vec<Combination>, done: Option<&State>) {
+        if self.objective != Objective::FewestItems {
+            return;
+        }
+        let mut fewest = done.map_or(usize::MAX, |done| done.items);
+        combinations.reverse();
+        combinations.retain(|combination| {
+            let keep = combination.items < fewest;
+            fewest = fewest.min(combination.items);
+            keep
+        });
+        combinations.reverse();
+    }
+
+    // Keeps the smallest sum, the largest sum and the combination with the fewest items of
+    // every bucket of close sums. Keeping the largest one lets the sums keep growing as small
+    // nodes are added, otherwise they would fall back into the same bucket and be dropped
+    // over and over.
+    fn thin(&mut self, combinations: &mut Vec<Combination>) {
+        let limit = match self.objective {
+            Objective::FewestBytes => THIN_ABOVE,
+            // A pruned frontier holds a single selection per number of items, which keeps it
+            // small enough to rarely need thinning
+            Objective::FewestItems => THIN_ABOVE << 2,
+        };
+        if combinations.len() <= limit {
+            return;
+        }
+        self.exact = false;
+        let step = self.to_free / BUCKETS + 1;
+        let mut thinned: Vec<Combination> = vec![];
+        let mut bucket = 0;
+        for combination in combinations.iter() {
+            if thinned
+                .last()
+                .is_none_or(|last| last.bytes / step != combination.bytes / step)
+            {
+                bucket = thinned.len();
+            }
+            match thinned.len() - bucket {
+                0..=2 => thinned.push(*combination),
+                // The smallest sum stays first and the largest one last
+                _ => {
+                    let previous = std::mem::replace(&mut thinned[bucket + 2], *combination);
+                    if previous.items < thinned[bucket + 1].items {
+                        thinned[bucket + 1] = previous;
+                    }
+                }
+            }
+        }
+        *combinations = thinned;
+    }
+}
+
+#[derive(Debug)]
+pub struct Plan {
+    pub disk: Disk,
+    pub used: u64,
+    pub to_free: u64,
+    pub objective: Objective,
+    pub candidates: Candidates,
+    pub deleted: Vec<(FsPath, u64)>,
+    // `false` when partial sums had to be merged, the plan may then not be the best one
+    pub exact: bool,
+    // Smallest single directory freeing enough space
+    pub single: Option<(FsPath, u64)>,
+}
+
+impl Plan {
+    pub fn freed(&self) -> u64 {
+        self.deleted.iter().map(|(_, size)| size).sum()
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "disk of {} bytes, {} used, {} required free : {} bytes to free",
+            self.disk.size, self.used, self.disk.required, self.to_free
+        )?;
+        if self.to_free == 0 {
+            return writeln!(f, "nothing to delete");
+        }
+        let (objective, candidates) = (
+            match self.objective {
+                Objective::FewestBytes => "bytes",
+                Objective::FewestItems => "items",
+            },
+            match self.candidates {
+                Candidates::Dirs => "directories",
+                Candidates::Files => "files",
+                Candidates::Any => "directories and files",
+            },
+        );
+        writeln!(
+            f,
+            "deleting {} item(s) among {candidates}, minimizing {objective}{}, frees {} bytes",
+            self.deleted.len(),
+            match self.exact {
+                true => "",
+                false => " (approximate)",
+            },
+            self.freed()
+        )?;
+        for (path, size) in self.deleted.iter() {
+            writeln!(f, "  {size}\t{path}")?;
+        }
+        match (self.exact, self.objective) {
+            (true, _) => (),
+            (false, Objective::FewestBytes) if self.freed() == self.to_free => writeln!(
+                f,
+                "close partial sums were merged to keep the search fast, no plan frees fewer \
+                 bytes but one with fewer items may exist"
+            )?,
+            (false, _) => writeln!(
+                f,
+                "close partial sums were merged to keep the search fast, a better plan may exist"
+            )?,
+        }
+        match self.single {
+            Some((ref path, size)) => {
+                writeln!(f, "a single directory, {path}, frees {size} bytes")?;
+                match size.cmp(&self.freed()) {
+                    std::cmp::Ordering::Greater => {
+                        writeln!(f, "the plan deletes {} bytes less", size - self.freed())
+                    }
+                    std::cmp::Ordering::Less => writeln!(
+                        f,
+                        "the plan deletes {} bytes more over {} items",
+                        self.freed() - size,
+                        self.deleted.len()
+                    ),
+                    std::cmp::Ordering::Equal => writeln!(f, "both free the same space"),
+                }
+            }
+            None => writeln!(f, "no single directory frees enough space"),
+        }
+    }
+}
+
+pub fn single_directory(filesystem: &FileSystem, sizes: &[u64], to_free: u64) -> Option<NodeId> {
+    filesystem
+        .ids()
+        .filter(|id| filesystem.is_dir(*id) && sizes[id.index()] >= to_free)
+        .min_by_key(|id| sizes[id.index()])
+}
+
+fn is_candidate(filesystem: &FileSystem, candidates: Candidates, id: NodeId) -> bool {
+    match (candidates, filesystem.is_dir(id)) {
+        _ if id == filesystem.root() => false,
+        (Candidates::Dirs, is_dir) => is_dir,
+        (Candidates::Files, is_dir) => !is_dir,
+        (Candidates::Any, _) => true,
+    }
+}
+
+// The highest candidates of every branch, together they free as much as can be freed
+fn largest_selection(filesystem: &FileSystem, candidates: Candidates) -> Vec<NodeId> {
+    let mut selection = vec![];
+    let mut pending = vec![filesystem.root()];
+    while let Some(id) = pending.pop() {
+        match is_candidate(filesystem, candidates, id) {
+            true => selection.push(id),
+            false => pending.extend(filesystem.children(id)),
+        }
+    }
+    selection
+}
+
+// Finds the non-nested nodes to delete with a knapsack over the tree: the selections of a
+// directory are the combinations of its children selections, plus the directory itself.
+// The root is never deleted. The plan is marked as not exact when close sums had to be
+// merged, which happens on most trees with a few hundred directories when minimizing bytes.
+pub fn plan(
+    filesystem: &FileSystem,
+    disk: Disk,
+    objective: Objective,
+    candidates: Candidates,
+) -> Result<Plan, String> {
+    let sizes = filesystem.sizes();
+    let used = sizes[filesystem.root().index()];
+    let to_free = disk.to_free(used);
+    let largest = largest_selection(filesystem, candidates);
+    let available = largest.iter().map(|id| sizes[id.index()]).sum::<u64>();
+    if available < to_free {
+        return Err(format!(
+            "Deleting every candidate frees {available} bytes, not the {to_free} bytes needed"
+        ));
+    }
+    let mut optimizer = Optimizer {
+        to_free,
+        objective,
+        exact: true,
+    };
+    let mut nodes = vec![];
+    if to_free > 0 {
+        let mut frontiers = filesystem.ids().map(|_| None).collect::<Vec<_>>();
+        // Children are stored after their parent
+        for id in filesystem.ids().collect::<Vec<NodeId>>().into_iter().rev() {
+            let mut frontier = optimizer.empty();
+            for child in filesystem.children(id) {
+                let child = frontiers[child.index()].take().unwrap();
+                frontier = optimizer.merge(frontier, child);
+            }
+            if is_candidate(filesystem, candidates, id) {
+                let state = State {
+                    bytes: sizes[id.index()],
+                    items: 1,
+                    selection: Rc::new(Selection::Node(id)),
+                };
+                optimizer.insert(&mut frontier, state);
+            }
+            frontiers[id.index()] = Some(frontier);
+        }
+        match frontiers[filesystem.root().index()]
+            .take()
+            .and_then(|frontier| frontier.done)
+        {
+            Some(best) => best.selection.collect(&mut nodes),
+            // Thinning always keeps the largest sums, this is only a safety net
+            None => {
+                optimizer.exact = false;
+                nodes = largest;
+            }
+        }
+    }
+    Ok(Plan {
+        disk,
+        used,
+        to_free,
+        objective,
+        candidates,
+        deleted: nodes
+            .into_iter()
+            .map(|id| (filesystem.path(id), sizes[id.index()]))
+            .collect(),
+        exact: optimizer.exact,
+        single: single_directory(filesystem, &sizes, to_free)
+            .map(|id| (filesystem.path(id), sizes[id.index()])),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Linear congruential generator, good enough to build varied trees
+    struct Random(u64);
+
+    impl Random {
+        fn below(&mut self, bound: u64) -> u64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (self.0 >> 33) % bound
+        }
+    }
+
+    fn random_tree(random: &mut Random, nodes: usize) -> FileSystem {
+        let mut filesystem = FileSystem::new();
+        let mut dirs = vec![filesystem.root()];
+        for index in 0..nodes {
+            let parent = dirs[random.below(dirs.len() as u64) as usize];
+            let name = format!("n{index}");
+            match random.below(3) {
+                0 => dirs.push(filesystem.add_dir(parent, &name).unwrap()),
+                _ => {
+                    let size = random.below(20) + 1;
+                    filesystem.add_file(parent, &name, size).unwrap();
+                }
+            }
+        }
+        filesystem
+    }
+
+    fn is_nested(filesystem: &FileSystem, selection: &[NodeId]) -> bool {
+        selection.iter().any(|id| {
+            let mut current = filesystem.node(*id).parent;
+            while let Some(parent) = current {
+                if selection.contains(&parent) {
+                    return true;
+                }
+                current = filesystem.node(parent).parent;
+            }
+            false
+        })
+    }
+
+    // Bytes and items of the best selection, trying every subset of candidates
+    fn brute_force(
+        filesystem: &FileSystem,
+        to_free: u64,
+        objective: Objective,
+        candidates: Candidates,
+    ) -> Option<(u64, usize)> {
+        let sizes = filesystem.sizes();
+        let ids = filesystem
+            .ids()
+            .filter(|id| is_candidate(filesystem, candidates, *id))
+            .collect::<Vec<NodeId>>();
+        let key = |(bytes, items): (u64, usize)| match objective {
+            Objective::FewestBytes => (bytes, items),
+            Objective::FewestItems => (items as u64, bytes as usize),
+        };
+        (0..1u32 << ids.len())
+            .map(|mask| {
+                (0..ids.len())
+                    .filter(|index| mask & 1 << index != 0)
+                    .map(|index| ids[index])
+                    .collect::<Vec<NodeId>>()
+            })
+            .filter(|selection| !is_nested(filesystem, selection))
+            .map(|selection| {
+                let bytes = selection.iter().map(|id| sizes[id.index()]).sum::<u64>();
+                (bytes, selection.len())
+            })
+            .filter(|(bytes, _)| *bytes >= to_free)
+            .min_by_key(|best| key(*best))
+    }
+
+    #[test]
+    fn plans_match_brute_force() {
+        let mut random = Random(7);
+        for _ in 0..100 {
+            let filesystem = random_tree(&mut random, 12);
+            let used = filesystem.sizes()[filesystem.root().index()];
+            let disk = Disk {
+                size: used,
+                required: random.below(used) + 1,
+            };
+            for objective in [Objective::FewestBytes, Objective::FewestItems] {
+                for candidates in [Candidates::Dirs, Candidates::Files, Candidates::Any] {
+                    let best = brute_force(&filesystem, disk.required, objective, candidates);
+                    let plan = plan(&filesystem, disk, objective, candidates);
+                    let Some((bytes, items)) = best else {
+                        assert!(plan.is_err());
+                        continue;
+                    };
+                    let plan = plan.unwrap();
+                    let deleted = plan
+                        .deleted
+                        .iter()
+                        .map(|(path, _)| filesystem.lookup(path).unwrap())
+                        .collect::<Vec<NodeId>>();
+                    assert!(plan.exact && !is_nested(&filesystem, &deleted));
+                    match objective {
+                        Objective::FewestBytes => {
+                            assert_eq!((plan.freed(), plan.deleted.len()), (bytes, items))
+                        }
+                        Objective::FewestItems => {
+                            assert!(plan.freed() >= disk.required);
+                            assert_eq!(plan.deleted.len(), items);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn many_small_files() {
+        let mut random = Random(11);
+        let mut filesystem = FileSystem::new();
+        let dir = filesystem.add_dir(filesystem.root(), "a").unwrap();
+        let mut sizes = (0..2000)
+            .map(|index| {
+                let size = random.below(7) + 1;
+                filesystem
+                    .add_file(dir, &format!("f{index}"), size)
+                    .unwrap();
+                size
+            })
+            .collect::<Vec<u64>>();
+        let used = sizes.iter().sum::<u64>();
+        let disk = Disk {
+            size: used,
+            required: used / 2,
+        };
+        // Biggest files first is the best choice when nothing is nested
+        sizes.sort_unstable_by(|a, b| b.cmp(a));
+        let fewest = sizes
+            .iter()
+            .scan(0, |freed, size| {
+                *freed += size;
+                Some(*freed)
+            })
+            .position(|freed| freed >= disk.required)
+            .unwrap()
+            + 1;
+
+        let bytes = plan(&filesystem, disk, Objective::FewestBytes, Candidates::Files).unwrap();
+        assert_eq!(bytes.freed(), disk.required);
+        let items = plan(&filesystem, disk, Objective::FewestItems, Candidates::Files).unwrap();
+        assert!(items.freed() >= disk.required);
+        assert_eq!(items.deleted.len(), fewest);
+
+        let too_much = Disk {
+            size: used,
+            required: used + 1,
+        };
+        assert!(plan(
+            &filesystem,
+            too_much,
+            Objective::FewestBytes,
+            Candidates::Files
+        )
+        .is_err());
+    }
+}
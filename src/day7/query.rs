@@ -0,0 +1,256 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use super::filesystem::{FileSystem, NodeId, NodeKind};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    File,
+    Dir,
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        match kind {
+            "f" | "file" => Ok(Kind::File),
+            "d" | "dir" => Ok(Kind::Dir),
+            _ => Err(format!("Unknown node type {:?}", kind)),
+        }
+    }
+}
+
+// Shell-like pattern where `*` matches any sequence of characters and `?` a single one
+#[derive(Debug, Clone, PartialEq)]
+pub struct Glob(Vec<char>);
+
+impl Glob {
+    pub fn matches(&self, name: &str) -> bool {
+        let name = name.chars().collect::<Vec<char>>();
+        let (mut pattern_index, mut name_index) = (0, 0);
+        // Position of the last `*` and of the name character it was matched against
+        let mut backtrack = None;
+        while name_index < name.len() {
+            match self.0.get(pattern_index) {
+                Some('*') => {
+                    backtrack = Some((pattern_index, name_index));
+                    pattern_index += 1;
+                    continue;
+                }
+                Some(c) if *c == '?' || *c == name[name_index] => {
+                    pattern_index += 1;
+                    name_index += 1;
+                    continue;
+                }
+                _ => (),
+            }
+            match backtrack {
+                // Let the last `*` swallow one more character
+                Some((star, matched)) => {
+                    backtrack = Some((star, matched + 1));
+                    pattern_index = star + 1;
+                    name_index = matched + 1;
+                }
+                None => return false,
+            }
+        }
+        self.0[pattern_index..].iter().all(|c| *c == '*')
+    }
+}
+
+impl FromStr for Glob {
+    type Err = String;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        Ok(Glob(pattern.chars().collect()))
+    }
+}
+
+// Same syntax as `find -size`: `+N` is more than N, `-N` less than N, `N` exactly N
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SizeFilter {
+    Above(u64),
+    Below(u64),
+    Exactly(u64),
+}
+
+impl SizeFilter {
+    pub fn matches(&self, size: u64) -> bool {
+        match *self {
+            SizeFilter::Above(limit) => size > limit,
+            SizeFilter::Below(limit) => size < limit,
+            SizeFilter::Exactly(limit) => size == limit,
+        }
+    }
+}
+
+impl FromStr for SizeFilter {
+    type Err = String;
+
+    fn from_str(filter: &str) -> Result<Self, Self::Err> {
+        let parse = |size: &str| {
+            size.parse::<u64>()
+                .map_err(|err| format!("Bad size {:?} : {err}", filter))
+        };
+        match (filter.strip_prefix('+'), filter.strip_prefix('-')) {
+            (Some(size), _) => parse(size).map(SizeFilter::Above),
+            (_, Some(size)) => parse(size).map(SizeFilter::Below),
+            _ => parse(filter).map(SizeFilter::Exactly),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Filter {
+    pub name: Option<Glob>,
+    pub size: Option<SizeFilter>,
+    pub kind: Option<Kind>,
+}
+
+// Answers questions about a filesystem, directory sizes are computed once up front
+pub struct Query<'a> {
+    filesystem: &'a FileSystem,
+    sizes: Vec<u64>,
+}
+
+impl<'a> Query<'a> {
+    pub fn new(filesystem: &'a FileSystem) -> Self {
+        Query {
+            filesystem,
+            sizes: filesystem.sizes(),
+        }
+    }
+
+    pub fn size(&self, id: NodeId) -> u64 {
+        self.sizes[id.index()]
+    }
+
+    fn kind(&self, id: NodeId) -> Kind {
+        match self.filesystem.is_dir(id) {
+            true => Kind::Dir,
+            false => Kind::File,
+        }
+    }
+
+    // Every node below `start` in depth-first order along with its depth, `start` being
+    // at depth 0. Nodes deeper than `max_depth` are skipped.
+    pub fn walk(&self, start: NodeId, max_depth: Option<usize>) -> Vec<(NodeId, usize)> {
+        let mut nodes = vec![];
+        let mut stack = vec![(start, 0)];
+        while let Some((id, depth)) = stack.pop() {
+            nodes.push((id, depth));
+            if max_depth.is_some_and(|max_depth| depth >= max_depth) {
+                continue;
+            }
+            stack.extend(
+                self.filesystem
+                    .children(id)
+                    .iter()
+                    .rev()
+                    .map(|child| (*child, depth + 1)),
+            );
+        }
+        nodes
+    }
+
+    // Directories below `start` with their total size, each one coming after its
+    // subdirectories like `du` does
+    pub fn du(&self, start: NodeId, max_depth: Option<usize>) -> Vec<(NodeId, u64)> {
+        let mut dirs = self
+            .walk(start, max_depth)
+            .into_iter()
+            .filter(|(id, _)| self.filesystem.is_dir(*id))
+            .map(|(id, _)| (id, self.size(id)))
+            .collect::<Vec<(NodeId, u64)>>();
+        dirs.reverse();
+        dirs
+    }
+
+    pub fn tree(&self, start: NodeId, max_depth: Option<usize>) -> Tree<'_> {
+        Tree {
+            query: self,
+            nodes: self.walk(start, max_depth),
+        }
+    }
+
+    pub fn find(&self, start: NodeId, filter: &Filter) -> Vec<NodeId> {
+        self.walk(start, None)
+            .into_iter()
+            .map(|(id, _)| id)
+            .filter(|id| {
+                filter
+                    .name
+                    .as_ref()
+                    .is_none_or(|glob| glob.matches(&self.filesystem.node(*id).name))
+                    && filter.size.is_none_or(|size| size.matches(self.size(*id)))
+                    && filter.kind.is_none_or(|kind| kind == self.kind(*id))
+            })
+            .collect()
+    }
+
+    // The `count` biggest nodes, all of them when `kind` is not given
+    pub fn largest(&self, count: usize, kind: Option<Kind>) -> Vec<NodeId> {
+        let mut nodes = self
+            .filesystem
+            .ids()
+            .filter(|id| kind.is_none_or(|kind| kind == self.kind(*id)))
+            .collect::<Vec<NodeId>>();
+        nodes.sort_by_key(|id| std::cmp::Reverse(self.size(*id)));
+        nodes.truncate(count);
+        nodes
+    }
+
+    // Total size and number of files per extension, biggest total first. Files without
+    // an extension are gathered under an empty one.
+    pub fn extensions(&self) -> Vec<(String, u64, usize)> {
+        let mut totals: HashMap<&str, (u64, usize)> = HashMap::new();
+        for id in self.filesystem.ids() {
+            let node = self.filesystem.node(id);
+            if let NodeKind::File(size) = node.kind {
+                let extension = match node.name.rsplit_once('.') {
+                    Some((stem, extension)) if !stem.is_empty() => extension,
+                    _ => "",
+                };
+                let total = totals.entry(extension).or_default();
+                total.0 += size;
+                total.1 += 1;
+            }
+        }
+        let mut totals = totals
+            .into_iter()
+            .map(|(extension, (size, count))| (extension.to_string(), size, count))
+            .collect::<Vec<(String, u64, usize)>>();
+        totals.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        totals
+    }
+}
+
+// Renders nodes the way the puzzle describes them, e.g. `- a (dir, size=94853)`
+pub struct Tree<'a> {
+    query: &'a Query<'a>,
+    nodes: Vec<(NodeId, usize)>,
+}
+
+impl fmt::Display for Tree<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let filesystem = self.query.filesystem;
+        for (id, depth) in self.nodes.iter() {
+            let name = match filesystem.node(*id).parent {
+                Some(_) => filesystem.node(*id).name.clone(),
+                None => String::from("/"),
+            };
+            let kind = match self.query.kind(*id) {
+                Kind::Dir => "dir",
+                Kind::File => "file",
+            };
+            writeln!(
+                f,
+                "{:indent$}- {name} ({kind}, size={})",
+                "",
+                self.query.size(*id),
+                indent = depth * 2
+            )?;
+        }
+        Ok(())
+    }
+}
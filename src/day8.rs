@@ -1,4 +1,4 @@
-use std::{fs, num::ParseIntError, str::FromStr};
+use std::{fs, str::FromStr};
 
 #[allow(unused)]
 const SAMPLE: &str = "30373
@@ -7,254 +7,494 @@ const SAMPLE: &str = "30373
 33549
 35390";
 
-struct Forest(Vec<Vec<u8>>);
+// Heights row after row. Only parsing builds a forest, which makes sure it is a non-empty
+// rectangle of digits.
+struct Forest {
+    width: usize,
+    trees: Vec<u8>,
+}
 
+// Direction a tree looks towards
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum Sight {
     Top,
     Bottom,
     Left,
     Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Sight {
+    // The lines of sight of the puzzle
+    const ORTHOGONAL: [Sight; 4] = [Sight::Top, Sight::Bottom, Sight::Left, Sight::Right];
+    const ALL: [Sight; 8] = [
+        Sight::Top,
+        Sight::Bottom,
+        Sight::Left,
+        Sight::Right,
+        Sight::TopLeft,
+        Sight::TopRight,
+        Sight::BottomLeft,
+        Sight::BottomRight,
+    ];
+
+    // Row and column offsets of one step towards `self`
+    fn direction(&self) -> (isize, isize) {
+        match self {
+            Sight::Top => (-1, 0),
+            Sight::Bottom => (1, 0),
+            Sight::Left => (0, -1),
+            Sight::Right => (0, 1),
+            Sight::TopLeft => (-1, -1),
+            Sight::TopRight => (-1, 1),
+            Sight::BottomLeft => (1, -1),
+            Sight::BottomRight => (1, 1),
+        }
+    }
+}
+
+// Eye position, which may be outside the forest, and eye height
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Viewpoint {
+    row: isize,
+    col: isize,
+    eye: f64,
 }
 
 impl std::fmt::Debug for Forest {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.0.iter().for_each(|inner| {
-            inner.iter().enumerate().for_each(|(index, number)| {
-                let _ = write!(
-                    f,
-                    "{number}{}",
-                    if index < (inner.len() - 1) { " " } else { "" }
-                );
-            });
-            let _ = write!(f, "\n");
-        });
-        Ok(())
+        self.write_rows(f, |_, _| String::new(), "")
     }
 }
 
-impl Forest {
-    fn get_tree_line(&self, sight: &Sight, line_index: usize, col_index: usize) -> Option<Vec<u8>> {
-        match sight {
-            Sight::Left | Sight::Right => match self.0.get(line_index) {
-                None => None,
-                Some(vec) => Some(vec.clone()),
+// For every tree, whether it can be seen from outside the forest and its scenic score
+struct Views {
+    visible: Vec<bool>,
+    scores: Vec<usize>,
+    // Set when a score did not fit in a `usize`, it is then capped at `usize::MAX`
+    saturated: bool,
+}
+
+impl Views {
+    fn count_visible_trees(&self) -> usize {
+        self.visible.iter().filter(|visible| **visible).count()
+    }
+
+    fn highest_score(&self) -> usize {
+        self.scores.iter().copied().max().unwrap_or(0)
+    }
+
+    // Index of the first tree with the highest scenic score
+    fn best_tree(&self) -> Option<usize> {
+        let best = self.highest_score();
+        self.scores.iter().position(|score| *score == best)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Layer {
+    Heights,
+    Visibility,
+    Scores,
+}
+
+impl FromStr for Layer {
+    type Err = String;
+
+    fn from_str(layer: &str) -> Result<Self, Self::Err> {
+        match layer {
+            "heights" => Ok(Layer::Heights),
+            "visibility" => Ok(Layer::Visibility),
+            "scores" => Ok(Layer::Scores),
+            _ => Err(format!("Unknown layer {:?}", layer)),
+        }
+    }
+}
+
+// Shades every tree by one layer, from black for the lowest values to white for the highest.
+// Scores are shaded on a logarithmic scale since a few trees dwarf all the others. Displaying
+// it prints the forest like `Debug` does, with ANSI colors.
+struct Heatmap<'a> {
+    forest: &'a Forest,
+    views: &'a Views,
+    layer: Layer,
+    best: Option<usize>,
+    highest: usize,
+}
+
+impl<'a> Heatmap<'a> {
+    fn new(forest: &'a Forest, views: &'a Views, layer: Layer) -> Self {
+        Heatmap {
+            forest,
+            views,
+            layer,
+            best: views.best_tree(),
+            highest: views.highest_score(),
+        }
+    }
+
+    fn level(&self, row: usize, col: usize) -> u8 {
+        let index = row * self.forest.width() + col;
+        match self.layer {
+            Layer::Heights => (self.forest.get(row, col) as u32 * 255 / 9) as u8,
+            Layer::Visibility => match self.views.visible[index] {
+                true => 255,
+                false => 64,
+            },
+            Layer::Scores => match self.highest {
+                0 => 0,
+                highest => {
+                    let score = self.views.scores[index] as f64;
+                    (score.ln_1p() / (highest as f64).ln_1p() * 255.0).round() as u8
+                }
             },
-            Sight::Top | Sight::Bottom => self
-                .0
-                .iter()
-                .map(|el| el.get(col_index).map(|number| number.clone()))
-                .collect::<Option<Vec<u8>>>(),
         }
     }
 
-    fn count_visible_trees(&self) -> usize {
-        self.0
-            .iter()
-            .enumerate()
-            .map(|(line_index, line)| {
-                line.iter()
-                    .enumerate()
-                    .map(move |(col_index, _)| {
-                        if col_index == 0
-                            || line_index == 0
-                            || col_index == line.len() - 1
-                            || line_index == self.0.len() - 1
-                        {
-                            true
-                        } else {
-                            vec![
-                                self.is_visible_trees(Sight::Top, line_index, col_index),
-                                self.is_visible_trees(Sight::Bottom, line_index, col_index),
-                                self.is_visible_trees(Sight::Left, line_index, col_index),
-                                self.is_visible_trees(Sight::Right, line_index, col_index),
-                            ]
-                            .iter()
-                            .flatten()
-                            .filter(|&&v| v)
-                            .count()
-                                > 0
-                        }
-                    })
-                    .collect::<Vec<bool>>()
-            })
-            .collect::<Vec<Vec<bool>>>()
-            .iter()
-            .flatten()
-            .filter(|v| **v)
-            .count()
+    fn header(&self, magic: &str) -> Vec<u8> {
+        format!(
+            "{magic}\n{} {}\n255\n",
+            self.forest.width(),
+            self.forest.height()
+        )
+        .into_bytes()
     }
 
-    fn is_visible_trees(
-        &self,
-        sight: Sight,
-        line_index: usize,
-        col_index: usize,
-    ) -> Result<bool, String> {
-        let line = self
-            .get_tree_line(&sight, line_index, col_index)
-            .ok_or(format!("line or column not found for index {line_index}"))?;
-        let target = match sight {
-            Sight::Top | Sight::Bottom => line.get(line_index).unwrap(),
-            Sight::Left | Sight::Right => line.get(col_index).unwrap(),
-        };
-        let res: bool = match sight {
-            Sight::Left => {
-                line.iter()
-                    .enumerate()
-                    .take_while(|(index, val)| **val < *target && *index < col_index)
-                    .count()
-                    >= col_index
-            }
-            Sight::Right => {
-                let val_index = transpose_index(col_index, line.len());
-                line.iter()
-                    .rev()
-                    .enumerate()
-                    .take_while(|(index, val)| **val < *target && *index < val_index)
-                    .count()
-                    >= val_index
-            }
-            Sight::Top => {
-                line.iter()
-                    .enumerate()
-                    .take_while(|(index, val)| **val < *target && *index < line_index)
-                    .count()
-                    >= line_index
+    fn cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.forest.height())
+            .flat_map(|row| (0..self.forest.width()).map(move |col| (row, col)))
+    }
+
+    // Binary greymap, one pixel per tree
+    fn pgm(&self) -> Vec<u8> {
+        let mut image = self.header("P5");
+        image.extend(self.cells().map(|(row, col)| self.level(row, col)));
+        image
+    }
+
+    // Binary pixmap, one pixel per tree with the best tree in red
+    fn ppm(&self) -> Vec<u8> {
+        let mut image = self.header("P6");
+        for (row, col) in self.cells() {
+            match self.best == Some(row * self.forest.width() + col) {
+                true => image.extend([255, 0, 0]),
+                false => image.extend([self.level(row, col); 3]),
             }
-            Sight::Bottom => {
-                let val_index = transpose_index(line_index, line.len());
-                line.iter()
-                    .rev()
-                    .enumerate()
-                    .take_while(|(index, val)| **val < *target && *index < val_index)
-                    .count()
-                    >= val_index
+        }
+        image
+    }
+}
+
+impl std::fmt::Display for Heatmap<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.forest.write_rows(
+            f,
+            |row, col| {
+                if self.best == Some(row * self.forest.width() + col) {
+                    return String::from("\x1b[1;38;5;231;48;5;196m");
+                }
+                // 232 to 255 is the greyscale ramp of the 256 colors palette
+                let level = self.level(row, col);
+                let foreground = if level > 127 { 16 } else { 231 };
+                format!(
+                    "\x1b[0;38;5;{foreground};48;5;{}m",
+                    232 + level as u32 * 23 / 255
+                )
+            },
+            "\x1b[0m",
+        )
+    }
+}
+
+impl Forest {
+    // Writes one line per row with the trees separated by spaces, each tree preceded by
+    // `style(row, col)` and each line ended by `end`
+    fn write_rows(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        style: impl Fn(usize, usize) -> String,
+        end: &str,
+    ) -> std::fmt::Result {
+        for (row, line) in self.trees.chunks(self.width).enumerate() {
+            for (col, tree) in line.iter().enumerate() {
+                let separator = if col + 1 < line.len() { " " } else { "" };
+                write!(f, "{}{tree}{separator}", style(row, col))?;
             }
+            writeln!(f, "{end}")?;
+        }
+        Ok(())
+    }
+
+    fn height(&self) -> usize {
+        self.trees.len() / self.width
+    }
+
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn get(&self, row: usize, col: usize) -> u8 {
+        self.trees[row * self.width + col]
+    }
+
+    fn tree(&self, row: isize, col: isize) -> Option<u8> {
+        let (row, col) = (usize::try_from(row).ok()?, usize::try_from(col).ok()?);
+        (row < self.height() && col < self.width).then(|| self.get(row, col))
+    }
+
+    // Walks every line from the edge in `sight`, keeping the trees not hidden yet by a taller
+    // one on a stack of decreasing heights. Once the lower trees are popped, the top of the
+    // stack is the first tree blocking the view, and an empty stack means the edge is visible.
+    // Rows are always read in order, the lines crossing them advance together one row at a
+    // time. A diagonal line is identified by the diagonal index of its cells.
+    fn sweep(&self, sight: &Sight, views: &mut Views) {
+        let (height, width) = (self.height(), self.width());
+        let (row_step, col_step) = sight.direction();
+        let lines = match (row_step, col_step) {
+            (_, 0) => width,
+            (0, _) => height,
+            _ => height + width - 1,
         };
-        Ok(res)
+        let mut stacks: Vec<Vec<(usize, u8)>> = vec![vec![]; lines];
+        for i in 0..height {
+            let row = if row_step > 0 { height - 1 - i } else { i };
+            for j in 0..width {
+                let col = if col_step > 0 { width - 1 - j } else { j };
+                let line = match (row_step, col_step) {
+                    (_, 0) => col,
+                    (0, _) => row,
+                    _ if row_step == col_step => row + width - 1 - col,
+                    _ => row + col,
+                };
+                // Distance to the edge in `sight`
+                let step = match (row_step, col_step) {
+                    (0, _) => j,
+                    (_, 0) => i,
+                    _ => i.min(j),
+                };
+                let tree = self.get(row, col);
+                let stack = &mut stacks[line];
+                while stack.last().is_some_and(|(_, top)| *top < tree) {
+                    stack.pop();
+                }
+                let index = row * width + col;
+                let distance = match stack.last() {
+                    Some((blocker, _)) => step - blocker,
+                    None => {
+                        views.visible[index] = true;
+                        step
+                    }
+                };
+                // The product of eight viewing distances can overflow on large forests
+                views.scores[index] = match views.scores[index].checked_mul(distance) {
+                    Some(score) => score,
+                    None => {
+                        views.saturated = true;
+                        usize::MAX
+                    }
+                };
+                stack.push((step, tree));
+            }
+        }
     }
 
-    fn get_tree_line_and_target_tree(
-        &self,
-        sight: &Sight,
-        line_index: &usize,
-        col_index: &usize,
-    ) -> Option<(Vec<u8>, &u8)> {
-        let line = self.get_tree_line(sight, *line_index, *col_index)?;
-        let target = self.0.get(*line_index)?.get(*col_index)?;
-        Some((line, target))
+    // Visibility from outside the forest and scenic scores using the puzzle rules
+    fn views(&self) -> Views {
+        self.views_along(&Sight::ORTHOGONAL)
     }
 
-    fn tree_score(&self, line_index: usize, col_index: usize) -> Result<usize, String> {
-        if line_index == 0
-            || col_index == 0
-            || line_index == self.0.len() - 1
-            || col_index == self.0.get(0).unwrap().len() - 1
-        {
-            Ok(0)
-        } else {
-            let count_no_rev = |vec: &Vec<u8>, target: &&u8, index: &usize| -> usize {
-                vec.iter()
-                    .skip(index + 1)
-                    .take_while(|&v| *v < **target)
-                    .count()
-            };
-
-            let count_rev = |vec: &Vec<u8>, target: &&u8, index: &usize| -> usize {
-                (*vec)
-                    .iter()
-                    .rev()
-                    .skip(index + 1)
-                    .take_while(|&v| *v < **target)
-                    .count()
-            };
-
-            let (line_top, target_top) = self
-                .get_tree_line_and_target_tree(&Sight::Top, &line_index, &col_index)
-                .ok_or(format!("line or column not found for index {line_index}"))?;
-
-            let (line_sides, target_sides) = self
-                .get_tree_line_and_target_tree(&Sight::Left, &line_index, &col_index)
-                .ok_or(format!("line or column not found for index {line_index}"))?;
-
-            let score_right_left = {
-                let val_index = transpose_index(col_index, line_sides.len());
-
-                let mut left_scores = count_no_rev(&line_sides, &target_sides, &col_index);
-                let mut right_scores = count_rev(&line_sides, &target_sides, &val_index);
-
-                left_scores += should_plus_one(left_scores, col_index, line_sides.len());
-                right_scores += should_plus_one(right_scores, val_index, line_sides.len());
-
-                left_scores * right_scores
-            };
-
-            let score_top_bottom = {
-                let val_index = transpose_index(line_index, line_top.len());
-
-                let mut top_scores = count_no_rev(&line_top, &target_top, &line_index);
-                let mut bottom_scores = count_rev(&line_top, &target_top, &val_index);
-
-                top_scores += should_plus_one(top_scores, line_index, line_top.len());
-                bottom_scores += should_plus_one(bottom_scores, val_index, line_top.len());
-
-                top_scores * bottom_scores
-            };
-
-            Ok(score_top_bottom * score_right_left)
+    fn views_along(&self, sights: &[Sight]) -> Views {
+        let size = self.height() * self.width();
+        let mut views = Views {
+            visible: vec![false; size],
+            scores: vec![1; size],
+            saturated: false,
+        };
+        for sight in sights {
+            self.sweep(sight, &mut views);
         }
+        views
     }
 
-    fn highest_score(&self) -> usize {
-        let res = self
-            .0
-            .iter()
-            .enumerate()
-            .map(|(line_index, line)| {
-                line.iter()
-                    .enumerate()
-                    .map(move |(col_index, _)| self.tree_score(line_index, col_index))
-                    .collect::<Result<Vec<usize>, String>>()
-            })
-            .collect::<Result<Vec<Vec<usize>>, String>>()
-            .unwrap();
-        *res.iter().flatten().max().unwrap()
+    // Viewpoint on top of a tree, looking from its height
+    fn viewpoint_at(&self, row: usize, col: usize) -> Option<Viewpoint> {
+        let (row, col) = (row as isize, col as isize);
+        self.tree(row, col).map(|tree| Viewpoint {
+            row,
+            col,
+            eye: tree as f64,
+        })
     }
-}
 
-fn should_plus_one(score: usize, index: usize, line_length: usize) -> usize {
-    if score + index + 1 <= line_length - 1 {
-        1
-    } else {
-        0
+    // Whether the top of the tree at `row`, `col` can be seen from `from`. The trees crossed
+    // on the way hide it when they reach the line between the eye and the top of the tree,
+    // which for a flat line is the puzzle rule of hiding behind trees at least as tall.
+    fn line_of_sight(&self, from: &Viewpoint, row: usize, col: usize) -> bool {
+        let Some(target) = self.tree(row as isize, col as isize) else {
+            return false;
+        };
+        let (row_offset, col_offset) = (row as isize - from.row, col as isize - from.col);
+        let steps = row_offset.abs().max(col_offset.abs());
+        (1..steps).all(|step| {
+            let ratio = step as f64 / steps as f64;
+            let crossed = self.tree(
+                from.row + (row_offset as f64 * ratio).round() as isize,
+                from.col + (col_offset as f64 * ratio).round() as isize,
+            );
+            let sight_line = from.eye + (target as f64 - from.eye) * ratio;
+            crossed.is_none_or(|crossed| (crossed as f64) < sight_line)
+        })
     }
-}
 
-fn transpose_index(index: usize, length: usize) -> usize {
-    length - 1 - index
+    // Trees whose top can be seen from `from`, not counting the one it stands on
+    fn visible_from(&self, from: &Viewpoint) -> Vec<(usize, usize)> {
+        (0..self.height())
+            .flat_map(|row| (0..self.width()).map(move |col| (row, col)))
+            .filter(|(row, col)| (*row as isize, *col as isize) != (from.row, from.col))
+            .filter(|(row, col)| self.line_of_sight(from, *row, *col))
+            .collect()
+    }
 }
 
 impl FromStr for Forest {
-    type Err = ParseIntError;
+    type Err = String;
 
     fn from_str(input: &str) -> Result<Self, Self::Err> {
-        let inner = input
-            .lines()
-            .map(|line| {
-                line.chars()
-                    .map(|character| character.to_string().parse::<u8>())
-                    .collect::<Result<Vec<u8>, ParseIntError>>()
-            })
-            .collect::<Result<Vec<Vec<u8>>, ParseIntError>>()?;
-        Ok(Forest(inner))
+        let mut width = None;
+        let mut trees = vec![];
+        for (index, line) in input.lines().enumerate() {
+            let line_number = index + 1;
+            let start = trees.len();
+            for (column, cell) in line.chars().enumerate() {
+                let height = cell.to_digit(10).ok_or_else(|| {
+                    format!(
+                        "line {line_number}, column {} : {:?} is not a tree height",
+                        column + 1,
+                        cell
+                    )
+                })?;
+                trees.push(height as u8);
+            }
+            let length = trees.len() - start;
+            match width {
+                None if length == 0 => return Err(format!("line {line_number} : empty row")),
+                None => width = Some(length),
+                Some(width) if width != length => {
+                    return Err(format!(
+                        "line {line_number} : {length} trees but the rows above have {width}"
+                    ))
+                }
+                Some(_) => (),
+            }
+        }
+        let width = width.ok_or("Empty forest")?;
+        Ok(Forest { width, trees })
     }
 }
 
-pub fn run_both_parts() -> Result<(), ParseIntError> {
+// Accepts `ROW,COL` on a tree, looking from its top, or `ROW,COL,EYE` anywhere
+fn parse_viewpoint(forest: &Forest, viewpoint: &str) -> Result<Viewpoint, String> {
+    let error = || format!("Bad viewpoint {:?}, expected ROW,COL[,EYE]", viewpoint);
+    let parts = viewpoint
+        .split(',')
+        .map(|part| part.trim())
+        .collect::<Vec<&str>>();
+    match parts[..] {
+        [row, col] => {
+            let (row, col) = (row.parse::<usize>(), col.parse::<usize>());
+            forest
+                .viewpoint_at(row.map_err(|_| error())?, col.map_err(|_| error())?)
+                .ok_or(String::from(
+                    "An eye height is needed outside of the forest",
+                ))
+        }
+        [row, col, eye] => Ok(Viewpoint {
+            row: row.parse().map_err(|_| error())?,
+            col: col.parse().map_err(|_| error())?,
+            eye: eye.parse().map_err(|_| error())?,
+        }),
+        _ => Err(error()),
+    }
+}
+
+fn read_forest() -> Result<Forest, String> {
     // let input = SAMPLE;
-    let input = fs::read_to_string("src/day8.input").unwrap();
-    let forest = input.parse::<Forest>()?;
-    println!("Visible trees : {:?}", forest.count_visible_trees());
-    println!("Highest tree score : {:?}", forest.highest_score());
+    let input = fs::read_to_string("src/day8.input").map_err(|err| err.to_string())?;
+    input.parse::<Forest>()
+}
+
+pub fn run_both_parts() -> Result<(), String> {
+    let forest = read_forest()?;
+    let views = forest.views();
+    println!("Visible trees : {:?}", views.count_visible_trees());
+    println!("Highest tree score : {:?}", views.highest_score());
+    Ok(())
+}
+
+// Accepts one of
+//   visible [--diagonals]
+//   from ROW,COL[,EYE]
+//   heatmap heights|visibility|scores [--diagonals] [--output PATH]
+// `--diagonals` adds the four diagonal lines of sight. A viewpoint may be outside the forest,
+// on a tree its eye height defaults to the height of the tree. The heatmap is printed with
+// ANSI colors, or written as a PGM or PPM image depending on the extension of `PATH`.
+pub fn run_command(args: &[String]) -> Result<(), String> {
+    let Some((command, args)) = args.split_first() else {
+        return run_both_parts();
+    };
+    let mut sights = &Sight::ORTHOGONAL[..];
+    let mut output = None;
+    let mut positional = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--diagonals" => sights = &Sight::ALL,
+            "--output" => output = Some(args.next().ok_or("--output expects a path")?),
+            other if positional.is_none() => positional = Some(other),
+            other => return Err(format!("Unknown argument {:?}", other)),
+        }
+    }
+    let forest = read_forest()?;
+    match command.as_str() {
+        "visible" => {
+            let views = forest.views_along(sights);
+            println!("Visible trees : {}", views.count_visible_trees());
+            println!("Highest tree score : {}", views.highest_score());
+            if views.saturated {
+                println!("Some scores overflowed and were capped at {}", usize::MAX);
+            }
+        }
+        "from" => {
+            let viewpoint =
+                parse_viewpoint(&forest, positional.ok_or("from expects a viewpoint")?)?;
+            let visible = forest.visible_from(&viewpoint);
+            for (row, col) in visible.iter() {
+                println!("{row},{col} : {}", forest.get(*row, *col));
+            }
+            println!("{} trees visible from {:?}", visible.len(), viewpoint);
+        }
+        "heatmap" => {
+            let layer = positional
+                .ok_or("heatmap expects a layer")?
+                .parse::<Layer>()?;
+            let views = forest.views_along(sights);
+            let heatmap = Heatmap::new(&forest, &views, layer);
+            match output {
+                None => print!("{heatmap}"),
+                Some(path) => {
+                    let image = match path.rsplit_once('.') {
+                        Some((_, "pgm")) => heatmap.pgm(),
+                        Some((_, "ppm")) => heatmap.ppm(),
+                        _ => return Err(format!("{path} : expected a .pgm or .ppm file")),
+                    };
+                    fs::write(path, image).map_err(|err| format!("{path} : {err}"))?;
+                }
+            }
+        }
+        _ => return Err(format!("Unknown command {:?}", command)),
+    }
     Ok(())
 }
@@ -59,16 +59,32 @@ impl std::ops::Add<&Direction> for (i16, i16) {
     }
 }
 
-#[derive(Default, Debug)]
+// A rope of knots, the first one is the head and every other knot follows the one before it
+#[derive(Debug)]
 struct Playground {
-    head: (i16, i16),
-    tail: (i16, i16),
+    knots: Vec<(i16, i16)>,
+    // Index of the knot whose positions are recorded
+    tracked: usize,
     visited: HashSet<(i16, i16)>,
 }
 
 impl Playground {
-    fn next_move(&self) -> (Option<Direction>, Option<Direction>) {
-        let (x, y) = self.distance();
+    fn new(length: usize, tracked: usize) -> Result<Self, String> {
+        if tracked >= length {
+            return Err(format!(
+                "Cannot track knot {tracked} of a rope of {length} knots"
+            ));
+        }
+        Ok(Playground {
+            knots: vec![(0, 0); length],
+            tracked,
+            visited: HashSet::from([(0, 0)]),
+        })
+    }
+
+    // Moves `tail` needs to make to stay next to `head`
+    fn next_move(head: (i16, i16), tail: (i16, i16)) -> (Option<Direction>, Option<Direction>) {
+        let (x, y) = Playground::distance(head, tail);
         if (-1..=1).contains(&(x.abs() + y.abs())) && x != 0 && y != 0 {
             (None, None)
         } else {
@@ -92,22 +108,25 @@ impl Playground {
 
     fn move_play(&mut self, move_dir: Move) {
         for _ in 0..move_dir.1 {
-            self.head = self.head + &move_dir.0;
-            let next_move = self.next_move();
-            self.tail = (
-                next_move
-                    .0
-                    .map_or(self.tail.0, |number: Direction| number + self.tail.0),
-                next_move
-                    .1
-                    .map_or(self.tail.1, |number: Direction| number + self.tail.1),
-            );
-            self.visited.insert(self.tail);
+            self.knots[0] = self.knots[0] + &move_dir.0;
+            for index in 1..self.knots.len() {
+                let (head, tail) = (self.knots[index - 1], self.knots[index]);
+                let next_move = Playground::next_move(head, tail);
+                self.knots[index] = (
+                    next_move
+                        .0
+                        .map_or(tail.0, |number: Direction| number + tail.0),
+                    next_move
+                        .1
+                        .map_or(tail.1, |number: Direction| number + tail.1),
+                );
+            }
+            self.visited.insert(self.knots[self.tracked]);
         }
     }
 
-    fn distance(&self) -> (i16, i16) {
-        (self.head.0 - self.tail.0, self.head.1 - self.tail.1)
+    fn distance(head: (i16, i16), tail: (i16, i16)) -> (i16, i16) {
+        (head.0 - tail.0, head.1 - tail.1)
     }
 }
 
@@ -121,6 +140,19 @@ D 1
 L 5
 R 2";
 
+#[allow(unused)]
+const LARGER_SAMPLE: &str = "R 5
+U 8
+L 8
+D 3
+R 17
+D 10
+L 25
+U 20";
+
+const PART_ONE_KNOTS: usize = 2;
+const PART_TWO_KNOTS: usize = 10;
+
 fn parse_move(input: &str) -> IResult<&str, Move> {
     map_res(is_a("UDRL 1234567890"), |res: &str| res.parse::<Move>())(input)
 }
@@ -129,12 +161,54 @@ fn parse_moves(input: &str) -> IResult<&str, Vec<Move>> {
     separated_list0(newline, parse_move)(input)
 }
 
+// Number of positions knot `tracked` of a rope of `length` knots goes through
+fn visited_positions(input: &str, length: usize, tracked: usize) -> Result<usize, String> {
+    let mut x = Playground::new(length, tracked)?;
+    let moves = parse_moves(input).map_err(|err| err.to_string())?.1;
+    moves.into_iter().for_each(|mov: Move| x.move_play(mov));
+    Ok(x.visited.len())
+}
+
+fn tail_positions(input: &str, length: usize) -> Result<usize, String> {
+    visited_positions(input, length, length.saturating_sub(1))
+}
+
+fn read_input() -> Result<String, String> {
+    fs::read_to_string("src/day9.input").map_err(|err| err.to_string())
+}
+
 pub fn run_part_one() -> Result<(), String> {
-    let mut x = Playground::default();
     // let input = SAMPLE;
-    let input = fs::read_to_string("src/day9.input").unwrap();
-    let moves = parse_moves(&input).map_err(|err| err.to_string())?.1;
-    moves.into_iter().for_each(|mov: Move| x.move_play(mov));
-    println!("visited knots : {}\n", x.visited.len());
+    let input = read_input()?;
+    println!(
+        "visited knots : {}\n",
+        tail_positions(&input, PART_ONE_KNOTS)?
+    );
+    Ok(())
+}
+
+pub fn run_part_two() -> Result<(), String> {
+    // let input = LARGER_SAMPLE;
+    let input = read_input()?;
+    println!(
+        "visited knots : {}\n",
+        tail_positions(&input, PART_TWO_KNOTS)?
+    );
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn part_one_sample() {
+        assert_eq!(tail_positions(SAMPLE, PART_ONE_KNOTS), Ok(13));
+    }
+
+    #[test]
+    fn part_two_samples() {
+        assert_eq!(tail_positions(SAMPLE, PART_TWO_KNOTS), Ok(1));
+        assert_eq!(tail_positions(LARGER_SAMPLE, PART_TWO_KNOTS), Ok(36));
+    }
+}
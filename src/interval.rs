@@ -0,0 +1,326 @@
+use std::{fmt, ops::RangeInclusive};
+
+pub trait Integer: Ord + Copy {
+    fn successor(self) -> Option<Self>;
+    fn predecessor(self) -> Option<Self>;
+    // Number of values in `start..=end`, zero when `end < start`
+    fn count(start: Self, end: Self) -> u128;
+}
+
+macro_rules! impl_integer {
+    ($($t:ty),*) => {
+        $(impl Integer for $t {
+            fn successor(self) -> Option<Self> {
+                self.checked_add(1)
+            }
+
+            fn predecessor(self) -> Option<Self> {
+                self.checked_sub(1)
+            }
+
+            fn count(start: Self, end: Self) -> u128 {
+                (end as i128 - start as i128 + 1).max(0) as u128
+            }
+        })*
+    };
+}
+
+impl_integer!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Interval<T> {
+    start: T,
+    end: T,
+}
+
+impl<T: Integer> Interval<T> {
+    pub fn new(start: T, end: T) -> Option<Self> {
+        (start <= end).then_some(Interval { start, end })
+    }
+
+    pub fn point(value: T) -> Self {
+        Interval {
+            start: value,
+            end: value,
+        }
+    }
+
+    pub fn start(&self) -> T {
+        self.start
+    }
+
+    pub fn end(&self) -> T {
+        self.end
+    }
+
+    pub fn len(&self) -> u128 {
+        T::count(self.start, self.end)
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        self.start <= value && value <= self.end
+    }
+
+    pub fn contains_interval(&self, other: &Self) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    // Overlapping or directly following each other, e.g. `1-3` and `4-6`
+    pub fn touches(&self, other: &Self) -> bool {
+        self.overlaps(other)
+            || self.end.successor() == Some(other.start)
+            || other.end.successor() == Some(self.start)
+    }
+
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        Interval::new(self.start.max(other.start), self.end.min(other.end))
+    }
+
+    // Only defined when the result is a single interval, see `IntervalSet` otherwise
+    pub fn union(&self, other: &Self) -> Option<Self> {
+        self.touches(other).then(|| Interval {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        })
+    }
+
+    pub fn difference(&self, other: &Self) -> Vec<Self> {
+        if !self.overlaps(other) {
+            return vec![*self];
+        }
+        let before = other
+            .start
+            .predecessor()
+            .and_then(|end| Interval::new(self.start, end));
+        let after = other
+            .end
+            .successor()
+            .and_then(|start| Interval::new(start, self.end));
+        before.into_iter().chain(after).collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = T> {
+        let end = self.end;
+        std::iter::successors(Some(self.start), move |value| {
+            value.successor().filter(|next| *next <= end)
+        })
+    }
+}
+
+impl<T: Integer> TryFrom<RangeInclusive<T>> for Interval<T> {
+    type Error = String;
+
+    fn try_from(range: RangeInclusive<T>) -> Result<Self, Self::Error> {
+        let (start, end) = range.into_inner();
+        Interval::new(start, end).ok_or(String::from("Empty interval"))
+    }
+}
+
+impl<T> From<Interval<T>> for RangeInclusive<T> {
+    fn from(interval: Interval<T>) -> Self {
+        interval.start..=interval.end
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Interval<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+// Sorted, disjoint and non-adjacent intervals
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntervalSet<T>(Vec<Interval<T>>);
+
+impl<T> Default for IntervalSet<T> {
+    fn default() -> Self {
+        IntervalSet(vec![])
+    }
+}
+
+impl<T: Integer> IntervalSet<T> {
+    pub fn new() -> Self {
+        IntervalSet::default()
+    }
+
+    pub fn intervals(&self) -> &[Interval<T>] {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> u128 {
+        self.0.iter().map(Interval::len).sum()
+    }
+
+    pub fn insert(&mut self, interval: Interval<T>) {
+        // Everything ending before `interval` starts is left untouched
+        let first = self
+            .0
+            .partition_point(|current| current.end < interval.start && !current.touches(&interval));
+        let mut merged = interval;
+        let mut last = first;
+        while let Some(union) = self.0.get(last).and_then(|current| current.union(&merged)) {
+            merged = union;
+            last += 1;
+        }
+        self.0.splice(first..last, [merged]);
+    }
+
+    pub fn remove(&mut self, interval: &Interval<T>) {
+        self.0 = self
+            .0
+            .iter()
+            .flat_map(|current| current.difference(interval))
+            .collect();
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        let index = self.0.partition_point(|current| current.end < value);
+        self.0
+            .get(index)
+            .is_some_and(|current| current.contains(value))
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        let mut result = self.clone();
+        other.0.iter().for_each(|interval| result.insert(*interval));
+        result
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let (mut left, mut right) = (0, 0);
+        let mut result = vec![];
+        while let (Some(a), Some(b)) = (self.0.get(left), other.0.get(right)) {
+            if let Some(common) = a.intersection(b) {
+                result.push(common);
+            }
+            if a.end < b.end {
+                left += 1;
+            } else {
+                right += 1;
+            }
+        }
+        IntervalSet(result)
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut result = self.clone();
+        other.0.iter().for_each(|interval| result.remove(interval));
+        result
+    }
+
+    // Values of `bounds` that are not covered by the set
+    pub fn gaps(&self, bounds: &Interval<T>) -> Self {
+        IntervalSet(vec![*bounds]).difference(self)
+    }
+}
+
+impl<T: Integer> FromIterator<Interval<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = Interval<T>>>(iter: I) -> Self {
+        let mut intervals = iter.into_iter().collect::<Vec<Interval<T>>>();
+        intervals.sort();
+        let mut merged: Vec<Interval<T>> = vec![];
+        for interval in intervals {
+            match merged
+                .last_mut()
+                .and_then(|last| last.union(&interval).map(|u| (last, u)))
+            {
+                Some((last, union)) => *last = union,
+                None => merged.push(interval),
+            }
+        }
+        IntervalSet(merged)
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for IntervalSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let intervals = self
+            .0
+            .iter()
+            .map(|interval| interval.to_string())
+            .collect::<Vec<String>>();
+        write!(f, "{{{}}}", intervals.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set<T: Integer>(intervals: &[(T, T)]) -> IntervalSet<T> {
+        intervals
+            .iter()
+            .map(|(start, end)| Interval::new(*start, *end).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn insert_merges_adjacent_intervals() {
+        let mut intervals = set(&[(1, 3), (7, 9)]);
+        intervals.insert(Interval::new(4, 6).unwrap());
+        assert_eq!(intervals, set(&[(1, 9)]));
+        intervals.insert(Interval::new(11, 12).unwrap());
+        assert_eq!(intervals, set(&[(1, 9), (11, 12)]));
+        assert_eq!(intervals.len(), 11);
+        assert!(intervals.contains(12) && !intervals.contains(10));
+    }
+
+    #[test]
+    fn difference_splits_intervals() {
+        let intervals = set(&[(1, 10)]);
+        assert_eq!(
+            intervals.difference(&set(&[(4, 5), (8, 8)])),
+            set(&[(1, 3), (6, 7), (9, 10)])
+        );
+        assert!(intervals.difference(&set(&[(0, 20)])).is_empty());
+        assert_eq!(
+            set(&[(1, 3), (6, 9)]).intersection(&set(&[(2, 7)])),
+            set(&[(2, 3), (6, 7)])
+        );
+        assert_eq!(
+            set(&[(1, 3)]).union(&set(&[(4, 4), (6, 7)])),
+            set(&[(1, 4), (6, 7)])
+        );
+    }
+
+    #[test]
+    fn gaps_at_the_bounds() {
+        let bounds = Interval::new(1, 10).unwrap();
+        assert_eq!(set(&[(3, 5)]).gaps(&bounds), set(&[(1, 2), (6, 10)]));
+        assert_eq!(set(&[(1, 5), (9, 10)]).gaps(&bounds), set(&[(6, 8)]));
+        assert_eq!(set(&[(0, 11)]).gaps(&bounds), IntervalSet::new());
+        assert_eq!(IntervalSet::new().gaps(&bounds), set(&[(1, 10)]));
+    }
+
+    #[test]
+    fn integer_edges() {
+        let full = Interval::new(0, u32::MAX).unwrap();
+        assert_eq!(full.len(), 1 << 32);
+        assert_eq!(
+            set(&[(0, 1), (u32::MAX - 1, u32::MAX)]).gaps(&full),
+            set(&[(2, u32::MAX - 2)])
+        );
+        let mut intervals = set(&[(u32::MAX, u32::MAX)]);
+        intervals.insert(Interval::new(0, u32::MAX - 1).unwrap());
+        assert_eq!(intervals, set(&[(0, u32::MAX)]));
+
+        let low = Interval::new(i64::MIN, i64::MIN + 2).unwrap();
+        assert_eq!(
+            low.difference(&Interval::point(i64::MIN)),
+            vec![Interval::new(i64::MIN + 1, i64::MIN + 2).unwrap()]
+        );
+        assert_eq!(
+            set(&[(i64::MIN, -1), (0, i64::MAX)]),
+            set(&[(i64::MIN, i64::MAX)])
+        );
+        assert_eq!(set(&[(i64::MIN, i64::MAX)]).len(), u64::MAX as u128 + 1);
+    }
+}
@@ -1,10 +1,10 @@
-
 mod day1;
 mod day10;
 mod day11;
 mod day12;
 mod day13;
 mod day14;
+mod day15;
 mod day2;
 mod day3;
 mod day4;
@@ -13,26 +13,70 @@ mod day6;
 mod day7;
 mod day8;
 mod day9;
+mod interval;
 
 fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    match args.iter().map(|arg| arg.as_str()).collect::<Vec<&str>>()[..] {
+        [] => run_all(),
+        ["day2", "tournament", ..] => match day2::run_tournament(&args[2..]) {
+            Ok(result) => print!("{result}"),
+            Err(err) => eprintln!("{err}"),
+        },
+        ["day3", ..] => {
+            if let Err(err) = day3::run_command(&args[1..]) {
+                eprintln!("{err}")
+            }
+        }
+        ["day4", ..] => {
+            if let Err(err) = day4::run_command(&args[1..]) {
+                eprintln!("{err}")
+            }
+        }
+        ["day5", ..] => {
+            if let Err(err) = day5::run_command(&args[1..]) {
+                eprintln!("{err}")
+            }
+        }
+        ["day6", ..] => {
+            if let Err(err) = day6::run_command(&args[1..]) {
+                eprintln!("{err}")
+            }
+        }
+        ["day7", ..] => {
+            if let Err(err) = day7::run_command(&args[1..]) {
+                eprintln!("{err}")
+            }
+        }
+        ["day8", ..] => {
+            if let Err(err) = day8::run_command(&args[1..]) {
+                eprintln!("{err}")
+            }
+        }
+        _ => eprintln!("Unknown command {:?}", args),
+    }
+}
+
+fn run_all() {
     println!("day 1: {:?}", day1::run_part_2());
     println!("day 2: {:?}", day2::run_part_one());
     println!("day 2: {:?}", day2::run_part_two());
-    println!("----------DAY-4------------");
-    day4::run_part_one();
-    day4::run_part_two();
-    println!("----------DAY-5------------");
-    day5::run_part_one();
-    day5::run_part_two();
-    println!("----------DAY-6------------");
-    day6::run_part_one();
-    day6::run_part_two();
-    println!("----------DAY-7------------");
-    day7::run_part_one();
-    day7::run_part_two();
+    println!("day 3: {:?}", day3::run_part_one());
+    println!("day 3: {:?}", day3::run_part_two(3));
+    println!("day 4: {:?}", day4::run_part_one());
+    println!("day 4: {:?}", day4::run_part_two());
+    println!("day 5: {:?}", day5::run_part_one());
+    println!("day 5: {:?}", day5::run_part_two());
+    println!("day 6: {:?}", day6::run_part_one());
+    println!("day 6: {:?}", day6::run_part_two());
+    println!("day 7: {:?}", day7::run_part_one());
+    println!("day 7: {:?}", day7::run_part_two());
     println!("----------DAY-8------------");
     day8::run_both_parts();
     day9::run_part_one();
+    if let Err(err) = day9::run_part_two() {
+        eprintln!("day 9: {err}")
+    }
     day10::run_part_one();
     day10::run_part_two();
     day11::run_part_one();